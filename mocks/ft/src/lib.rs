@@ -0,0 +1,133 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{env, near_bindgen, ext_contract, assert_one_yocto, serde_json};
+use near_sdk::{Promise, PromiseResult, Balance, Gas, PanicOnDefault};
+use near_sdk::json_types::U128;
+use near_sdk::AccountId;
+use std::collections::HashMap;
+
+near_sdk::setup_alloc!();
+
+const GAS_FOR_FT_ON_TRANSFER: Gas = 25_000_000_000_000;
+const GAS_FOR_RESOLVE_TRANSFER: Gas = 10_000_000_000_000;
+
+#[ext_contract(ext_receiver)]
+pub trait FungibleTokenReceiver {
+    fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> near_sdk::PromiseOrValue<U128>;
+}
+
+#[ext_contract(ext_self)]
+pub trait FungibleTokenResolver {
+    fn ft_resolve_transfer(&mut self, sender_id: AccountId, receiver_id: AccountId, amount: U128) -> U128;
+}
+
+/// A NEP-141 token for the simulation tests,
+/// an account must be registered before it receives tokens
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct MockFt {
+    balances: HashMap<AccountId, Balance>
+}
+
+#[near_bindgen]
+impl MockFt {
+    #[init]
+    pub fn new(owner_id: AccountId, total_supply: U128) -> Self {
+        let mut balances = HashMap::new();
+        balances.insert(owner_id, total_supply.0);
+        Self {
+            balances
+        }
+    }
+
+    /// Stands for `storage_deposit`, without the deposit
+    pub fn register(&mut self, account_id: AccountId) {
+        self.balances.entry(account_id).or_insert(0);
+    }
+
+    /// Stands for `storage_unregister`, the balance must be empty
+    pub fn unregister(&mut self) {
+        let account_id = env::predecessor_account_id();
+        assert_eq!(
+            self.balances.get(&account_id).copied().unwrap_or(0),
+            0,
+            "The balance of {} is not empty", account_id
+        );
+        self.balances.remove(&account_id);
+    }
+
+    pub fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+        U128(self.balances.get(&account_id).copied().unwrap_or(0))
+    }
+
+    #[payable]
+    pub fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        assert_one_yocto();
+        self.transfer(&env::predecessor_account_id(), &receiver_id, amount.0);
+        if let Some(memo) = memo {
+            env::log(memo.as_bytes());
+        }
+    }
+
+    #[payable]
+    pub fn ft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String
+    ) -> Promise {
+        assert_one_yocto();
+        let sender_id = env::predecessor_account_id();
+        self.transfer(&sender_id, &receiver_id, amount.0);
+        if let Some(memo) = memo {
+            env::log(memo.as_bytes());
+        }
+
+        ext_receiver::ft_on_transfer(
+            sender_id.clone(),
+            amount,
+            msg,
+            &receiver_id,
+            0,
+            GAS_FOR_FT_ON_TRANSFER
+        ).then(ext_self::ft_resolve_transfer(
+            sender_id,
+            receiver_id.clone(),
+            amount,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_RESOLVE_TRANSFER
+        ))
+    }
+
+    /// Gives the unused amount back to the sender, all of it when `ft_on_transfer` failed.
+    /// Returns the amount used
+    #[private]
+    pub fn ft_resolve_transfer(&mut self, sender_id: AccountId, receiver_id: AccountId, amount: U128) -> U128 {
+        let unused = match env::promise_result(0) {
+            PromiseResult::Successful(value) => serde_json::from_slice::<U128>(&value)
+                .map(|unused| std::cmp::min(unused.0, amount.0))
+                .unwrap_or(amount.0),
+            _ => amount.0
+        };
+        if unused > 0 {
+            self.transfer(&receiver_id, &sender_id, unused);
+        }
+        U128(amount.0 - unused)
+    }
+
+    fn transfer(&mut self, sender_id: &AccountId, receiver_id: &AccountId, amount: Balance) {
+        assert!(amount > 0, "The amount must be positive");
+        assert!(
+            self.balances.contains_key(receiver_id),
+            "The account {} is not registered", receiver_id
+        );
+        let balance = self.balances.get(sender_id).copied().unwrap_or(0);
+        assert!(
+            balance >= amount,
+            "The account {} does not have enough balance", sender_id
+        );
+        self.balances.insert(sender_id.clone(), balance - amount);
+        *self.balances.get_mut(receiver_id).unwrap() += amount;
+    }
+}
@@ -0,0 +1,81 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{env, near_bindgen, PanicOnDefault};
+use near_sdk::json_types::{U64, U128};
+use near_sdk::serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+near_sdk::setup_alloc!();
+
+// The price oracle interface, see https://github.com/NearDeFi/price-oracle
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone)]
+pub struct Price {
+    pub multiplier: U128,
+    pub decimals: u8
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetOptionalPrice {
+    pub asset_id: String,
+    pub price: Option<Price>
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceData {
+    pub timestamp: U64,     // nanoseconds
+    pub recency_duration_sec: u32,
+    pub prices: Vec<AssetOptionalPrice>
+}
+
+/// A price oracle for the simulation tests, anyone can set a price.
+/// The price data is as old as the last price set
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct MockOracle {
+    prices: HashMap<String, Price>,
+    timestamp: u64
+}
+
+#[near_bindgen]
+impl MockOracle {
+    #[init]
+    pub fn new() -> Self {
+        Self {
+            prices: HashMap::new(),
+            timestamp: env::block_timestamp()
+        }
+    }
+
+    /// `None` removes the price of the asset
+    pub fn set_price(&mut self, asset_id: String, price: Option<Price>) {
+        match price {
+            Some(price) => {
+                self.prices.insert(asset_id, price);
+            },
+            None => {
+                self.prices.remove(&asset_id);
+            }
+        }
+        self.timestamp = env::block_timestamp();
+    }
+
+    /// Every asset with a price when `asset_ids` is not given
+    pub fn get_price_data(&self, asset_ids: Option<Vec<String>>) -> PriceData {
+        let asset_ids = asset_ids.unwrap_or_else(|| self.prices.keys().cloned().collect());
+        PriceData {
+            timestamp: U64(self.timestamp),
+            recency_duration_sec: 90,
+            prices: asset_ids
+                .into_iter()
+                .map(|asset_id| AssetOptionalPrice {
+                    price: self.prices.get(&asset_id).cloned(),
+                    asset_id
+                })
+                .collect()
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use near_sdk::AccountId;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};  
+use near_sdk::{serde::{Serialize, Deserialize}};
+use crate::types::*;
+
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Airline {
+    airline_id: AirlineId,
+    iata_code: String,
+    payment_account: AccountId,
+    weight_tolerance: Weight
+}
+
+impl Airline {
+    pub fn new(
+        airline_id: AirlineId,
+        iata_code: String,
+        payment_account: AccountId
+    ) -> Self {
+        Self {
+            airline_id,
+            iata_code,
+            payment_account,
+            weight_tolerance: 0.0
+        }
+    }
+
+    pub fn get_id(&self) -> &AirlineId {
+        &self.airline_id
+    }
+
+    pub fn get_iata_code(&self) -> &String {
+        &self.iata_code
+    }
+
+    pub fn get_payment_account(&self) -> &AccountId {
+        &self.payment_account
+    }
+
+    pub fn get_weight_tolerance(&self) -> &Weight {
+        &self.weight_tolerance
+    }
+
+    pub fn set_weight_tolerance(&mut self, weight_tolerance: Weight) {
+        self.weight_tolerance = weight_tolerance;
+    }
+
+    pub fn set_payment_account(&mut self, payment_account: AccountId) {
+        self.payment_account = payment_account;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,118 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{serde::{Serialize, Deserialize}};
+use crate::types::*;
+
+/// The coordinates of an airport in microdegrees (1/1,000,000 of a degree),
+/// keyed by its IATA code
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Airport {
+    pub latitude: i32,
+    pub longitude: i32
+}
+
+// Fixed-point numbers are scaled by 10^18
+const SCALE: i128 = 1_000_000_000_000_000_000;
+const PI: i128 = 3_141_592_653_589_793_238;
+const MICRODEGREES_PER_HALF_TURN: i128 = 180_000_000;
+// The mean radius of the Earth, in 1/1000 miles
+const EARTH_RADIUS: i128 = 3_958_761;
+
+impl Airport {
+    pub fn assert_valid(&self) {
+        assert!(
+            self.latitude.abs() <= 90_000_000 && self.longitude.abs() <= 180_000_000,
+            "The coordinates of an airport are out of range"
+        );
+    }
+
+    /// The great-circle distance with the haversine formula,
+    /// computed in fixed-point so that every node gets the same miles
+    pub fn get_distance(&self, other: &Airport) -> Distance {
+        let latitude = to_radians(self.latitude);
+        let other_latitude = to_radians(other.latitude);
+        let half_latitude = (other_latitude - latitude) / 2;
+        let half_longitude = (to_radians(other.longitude) - to_radians(self.longitude)) / 2;
+
+        let haversine = mul(sin(half_latitude), sin(half_latitude))
+            + mul(
+                mul(cos(latitude), cos(other_latitude)),
+                mul(sin(half_longitude), sin(half_longitude))
+            );
+        let angle = 2 * asin(sqrt(haversine.clamp(0, SCALE)));
+        // Rounded to the nearest mile
+        ((angle * EARTH_RADIUS / SCALE + 500) / 1000) as Distance
+    }
+}
+
+/// IATA airport codes are three capital letters
+pub fn assert_iata_code(code: &str) {
+    assert!(
+        code.len() == 3 && code.bytes().all(|byte| byte.is_ascii_uppercase()),
+        "The airport code must be 3 capital letters"
+    );
+}
+
+fn to_radians(microdegrees: i32) -> i128 {
+    i128::from(microdegrees) * PI / MICRODEGREES_PER_HALF_TURN
+}
+
+fn mul(a: i128, b: i128) -> i128 {
+    a * b / SCALE
+}
+
+/// Taylor series, after bringing `x` back to [-PI, PI]
+fn sin(x: i128) -> i128 {
+    let mut x = x % (2 * PI);
+    if x > PI {
+        x -= 2 * PI;
+    } else if x < -PI {
+        x += 2 * PI;
+    }
+    let square = mul(x, x);
+    let mut term = x;
+    let mut sum = x;
+    let mut n = 1;
+    while term != 0 {
+        term = -mul(term, square) / ((n + 1) * (n + 2));
+        sum += term;
+        n += 2;
+    }
+    sum
+}
+
+fn cos(x: i128) -> i128 {
+    sin(PI / 2 - x)
+}
+
+/// Bisection over [0, PI / 2], as `sin` is increasing there
+fn asin(x: i128) -> i128 {
+    let mut low = 0;
+    let mut high = PI / 2;
+    while high - low > 1 {
+        let middle = (low + high) / 2;
+        if sin(middle) < x {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    low
+}
+
+/// Newton's method on the integer square root of `x * SCALE`
+fn sqrt(x: i128) -> i128 {
+    let value = x * SCALE;
+    if value == 0 {
+        return 0;
+    }
+    let mut root = value;
+    let mut next = (root + 1) / 2;
+    while next < root {
+        root = next;
+        next = (root + value / root) / 2;
+    }
+    root
+}
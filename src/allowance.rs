@@ -0,0 +1,79 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{serde::{Serialize, Deserialize}};
+use crate::types::*;
+
+/// The baggages a customer can check in a flight,
+/// the weight limits are not checked when they are not set
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Allowance {
+    pub max_baggages: u8,
+    pub max_baggage_weight: Option<Weight>,
+    pub max_total_weight: Option<Weight>
+}
+
+impl Default for Allowance {
+    fn default() -> Self {
+        Self {
+            max_baggages: 3,
+            max_baggage_weight: None,
+            max_total_weight: None
+        }
+    }
+}
+
+impl Allowance {
+    pub fn assert_valid(&self) {
+        for limit in [self.max_baggage_weight, self.max_total_weight].iter().flatten() {
+            assert!(
+                *limit > 0.0,
+                "Weight limits must be positive"
+            );
+        }
+    }
+
+    /// The allowance shared by the members of a group,
+    /// a baggage keeps its own weight limit
+    pub fn pooled(&self, members: usize) -> Self {
+        Self {
+            max_baggages: (usize::from(self.max_baggages) * members).min(usize::from(u8::MAX)) as u8,
+            max_baggage_weight: self.max_baggage_weight,
+            max_total_weight: self.max_total_weight.map(|weight| weight * members as Weight)
+        }
+    }
+
+    /// Checks the baggages already added, when the allowance shrinks
+    pub fn assert_within(&self, baggage_len: u64, total_weight: Weight) {
+        if baggage_len > u64::from(self.max_baggages) {
+            panic!("The baggages cannot be more than {}", self.max_baggages);
+        }
+        if let Some(max_total_weight) = self.max_total_weight {
+            if total_weight > max_total_weight {
+                panic!("Your baggages cannot weigh more than {} lbs in total", max_total_weight);
+            }
+        }
+    }
+
+    /// Checks a new baggage,`total_weight` is the weight of the baggages already checked
+    pub fn assert_baggage(&self, baggage_len: u64, total_weight: Weight, baggage_weight: Weight) {
+        assert!(
+            baggage_weight > 0.0,
+            "The baggage weight must be positive"
+        );
+        if baggage_len >= u64::from(self.max_baggages) {
+            panic!("You cannot add more than {} baggages", self.max_baggages);
+        }
+        if let Some(max_baggage_weight) = self.max_baggage_weight {
+            if baggage_weight > max_baggage_weight {
+                panic!("A baggage cannot weigh more than {} lbs", max_baggage_weight);
+            }
+        }
+        if let Some(max_total_weight) = self.max_total_weight {
+            if total_weight + baggage_weight > max_total_weight {
+                panic!("Your baggages cannot weigh more than {} lbs in total", max_total_weight);
+            }
+        }
+    }
+}
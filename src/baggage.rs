@@ -1,20 +1,100 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};  
 use near_sdk::{serde::{Serialize, Deserialize}};
 use crate::types::*;
+use crate::dangerous_goods::*;
 
+/// The size of a baggage in inches
 #[derive(BorshDeserialize, BorshSerialize)]
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dimensions {
+    pub length: Length,
+    pub width: Length,
+    pub height: Length
+}
+
+impl Dimensions {
+    pub fn assert_valid(&self) {
+        assert!(
+            self.length > 0.0 && self.width > 0.0 && self.height > 0.0,
+            "The baggage dimensions must be positive"
+        );
+    }
+
+    /// Length + width + height, as measured by the airlines
+    pub fn get_linear(&self) -> Length {
+        self.length + self.width + self.height
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BaggageKind {
+    #[default]
+    Suitcase,
+    SportsEquipment,
+    MusicalInstrument,
+    Pet,
+    MobilityAid
+}
+
+/// An unmeasured weight is `null` in JSON
+mod unmeasured_as_null {
+    use near_sdk::serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use crate::types::*;
+
+    pub fn serialize<S: Serializer>(weight: &Weight, serializer: S) -> Result<S::Ok, S::Error> {
+        Some(*weight).filter(|weight| *weight > 0.0).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Weight, D::Error> {
+        Ok(Option::<Weight>::deserialize(deserializer)?.unwrap_or(0.0))
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone)]
 pub struct Baggage {
     baggage_id: BaggageId,
-    baggage_weight: Weight
+    baggage_weight: Weight,
+    /// 0 until the baggage is weighed at the counter, so that recording
+    /// the weight does not grow the storage paid by the customer
+    #[serde(with = "unmeasured_as_null")]
+    measured_weight: Weight,
+    billed_weight: Weight,
+    dimensions: Option<Dimensions>,
+    kind: BaggageKind,
+    dangerous_goods: Vec<DeclaredItem>,
+    inspection: InspectionState,
+    /// Picked up by the customer or a delegate
+    #[serde(default)]
+    claimed: bool
 }
 
 impl Baggage {
-    pub fn new(baggage_id: BaggageId, baggage_weight: Weight) -> Self {
+    pub fn new(
+        baggage_id: BaggageId,
+        baggage_weight: Weight,
+        dimensions: Option<Dimensions>,
+        kind: BaggageKind,
+        dangerous_goods: Vec<DeclaredItem>,
+        inspection: InspectionState
+    ) -> Self {
         Self {
             baggage_id,
-            baggage_weight
+            baggage_weight,
+            measured_weight: 0.0,
+            billed_weight: baggage_weight,
+            dimensions,
+            kind,
+            dangerous_goods,
+            inspection,
+            claimed: false
         }
     }
 
@@ -25,4 +105,59 @@ impl Baggage {
     pub fn get_weight(&self) -> &Weight {
         &self.baggage_weight
     }
-}
\ No newline at end of file
+
+    pub fn get_measured_weight(&self) -> Option<Weight> {
+        Some(self.measured_weight).filter(|weight| *weight > 0.0)
+    }
+
+    /// The declared weight, or the measured one when it is out of the tolerance
+    pub fn get_billed_weight(&self) -> &Weight {
+        &self.billed_weight
+    }
+
+    pub fn record_weight(&mut self, measured_weight: Weight, tolerance: Weight) {
+        self.measured_weight = measured_weight;
+        self.billed_weight = if (measured_weight - self.baggage_weight).abs() <= tolerance {
+            self.baggage_weight
+        } else {
+            measured_weight
+        };
+    }
+
+    pub fn get_dimensions(&self) -> &Option<Dimensions> {
+        &self.dimensions
+    }
+
+    pub fn get_kind(&self) -> &BaggageKind {
+        &self.kind
+    }
+
+    pub fn get_dangerous_goods(&self) -> &Vec<DeclaredItem> {
+        &self.dangerous_goods
+    }
+
+    pub fn get_inspection(&self) -> &InspectionState {
+        &self.inspection
+    }
+
+    pub fn clear_inspection(&mut self) {
+        assert_eq!(
+            self.inspection,
+            InspectionState::Pending,
+            "This baggage does not need an inspection"
+        );
+        self.inspection = InspectionState::Cleared;
+    }
+
+    pub fn is_claimed(&self) -> bool {
+        self.claimed
+    }
+
+    pub fn claim(&mut self) {
+        self.claimed = true;
+    }
+
+    pub fn get_linear_dimension(&self) -> Option<Length> {
+        self.dimensions.as_ref().map(|dimensions| dimensions.get_linear())
+    }
+}
@@ -0,0 +1,95 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{serde::{Serialize, Deserialize}};
+
+/// The restricted items a customer declares in a checked baggage,
+/// after the IATA Dangerous Goods Regulations
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DangerousItem {
+    /// Batteries installed in a device
+    LithiumBatteries,
+    SpareLithiumBatteries,
+    Aerosols,
+    DryIce,
+    MagnetizedMaterials,
+    CompressedGases,
+    FlammableLiquids,
+    Corrosives,
+    Explosives
+}
+
+/// What happens to a baggage declaring an item
+#[derive(Debug, PartialEq)]
+pub enum DangerousGoodsRule {
+    Allowed,
+    Inspect,
+    Forbidden
+}
+
+impl DangerousItem {
+    /// Toiletry aerosols are allowed up to 4 cans
+    pub fn get_rule(&self, quantity: u32) -> DangerousGoodsRule {
+        match self {
+            DangerousItem::Aerosols if quantity <= 4 => DangerousGoodsRule::Allowed,
+            DangerousItem::Aerosols
+            | DangerousItem::LithiumBatteries
+            | DangerousItem::DryIce
+            | DangerousItem::MagnetizedMaterials => DangerousGoodsRule::Inspect,
+            DangerousItem::SpareLithiumBatteries
+            | DangerousItem::CompressedGases
+            | DangerousItem::FlammableLiquids
+            | DangerousItem::Corrosives
+            | DangerousItem::Explosives => DangerousGoodsRule::Forbidden
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeclaredItem {
+    pub item: DangerousItem,
+    pub quantity: u32
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InspectionState {
+    NotRequired,
+    Pending,
+    Cleared
+}
+
+/// Rejects the forbidden items, then tells if the baggage must be inspected.
+/// An item declared several times is checked on its total quantity
+pub fn check_declaration(declaration: &[DeclaredItem]) -> InspectionState {
+    let mut state = InspectionState::NotRequired;
+    for (index, declared) in declaration.iter().enumerate() {
+        assert!(
+            declared.quantity > 0,
+            "The quantity of a declared item must be positive"
+        );
+        if declaration[..index].iter().any(|other| other.item == declared.item) {
+            continue;
+        }
+        let quantity = declaration[index..]
+            .iter()
+            .filter(|other| other.item == declared.item)
+            .fold(0u32, |total, other| total.saturating_add(other.quantity));
+        match declared.item.get_rule(quantity) {
+            DangerousGoodsRule::Allowed => {},
+            DangerousGoodsRule::Inspect => {
+                state = InspectionState::Pending;
+            },
+            DangerousGoodsRule::Forbidden => {
+                panic!("{:?} cannot be carried in a checked baggage", declared.item);
+            }
+        }
+    }
+    state
+}
@@ -0,0 +1,34 @@
+use near_sdk::{env, AccountId};
+use near_sdk::json_types::U64;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{serde::{Serialize, Deserialize}};
+use crate::types::*;
+
+/// The delegates a customer can authorize for a flight
+pub const MAX_DELEGATIONS: usize = 3;
+
+/// An account allowed to claim the baggages of a customer,
+/// all of them when `baggage_ids` is not set
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Delegation {
+    pub delegate: AccountId,
+    pub baggage_ids: Option<Vec<BaggageId>>,
+    /// In nanoseconds, the delegation never expires when it is not set
+    pub expires_at: Option<U64>
+}
+
+impl Delegation {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| env::block_timestamp() >= expires_at.0)
+    }
+
+    pub fn covers(&self, baggage_id: BaggageId) -> bool {
+        self.baggage_ids
+            .as_ref()
+            .is_none_or(|baggage_ids| baggage_ids.contains(&baggage_id))
+    }
+}
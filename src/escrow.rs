@@ -0,0 +1,89 @@
+use near_sdk::{AccountId, Balance};
+use near_sdk::json_types::U128;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};  
+use near_sdk::{serde::{Serialize, Deserialize}};
+use crate::types::*;
+
+/// The deposit held by the contract for a flight,
+/// `token_id` is `None` when it was paid in NEAR.
+/// It covers `fee` USD, one USD being `rate` units of the token
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Debug, PartialEq, Clone)]
+pub struct Escrow {
+    payer: AccountId,
+    token_id: Option<AccountId>,
+    amount: U128,
+    fee: U128,
+    rate: U128
+}
+
+impl Escrow {
+    pub fn new(
+        payer: AccountId,
+        token_id: Option<AccountId>,
+        amount: Balance,
+        fee: Balance,
+        rate: Balance
+    ) -> Self {
+        Self {
+            payer,
+            token_id,
+            amount: U128(amount),
+            fee: U128(fee),
+            rate: U128(rate)
+        }
+    }
+
+    pub fn get_payer(&self) -> &AccountId {
+        &self.payer
+    }
+
+    pub fn get_token_id(&self) -> &Option<AccountId> {
+        &self.token_id
+    }
+
+    pub fn get_amount(&self) -> Balance {
+        self.amount.0
+    }
+
+    pub fn get_fee(&self) -> Balance {
+        self.fee.0
+    }
+
+    pub fn get_rate(&self) -> Balance {
+        self.rate.0
+    }
+
+    /// The amount to pay when the fee grows to `fee`, at the rate of the deposit
+    pub fn get_top_up(&self, fee: Balance) -> Balance {
+        fee.saturating_sub(self.fee.0) * self.rate.0
+    }
+
+    pub fn top_up(&mut self, amount: Balance, fee: Balance) {
+        self.amount = U128(self.amount.0 + amount);
+        self.fee = U128(fee);
+    }
+
+    /// Lowers the fee to `fee`, returns the amount to give back
+    pub fn release(&mut self, fee: Balance) -> Balance {
+        let amount = std::cmp::min(
+            self.fee.0.saturating_sub(fee) * self.rate.0,
+            self.amount.0
+        );
+        self.amount = U128(self.amount.0 - amount);
+        self.fee = U128(fee);
+        amount
+    }
+}
+
+/// The `msg` of `ft_transfer_call` when paying for a flight,
+/// `customer_id` is set when paying for another customer
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TransferMessage {
+    pub flight_id: FlightId,
+    #[serde(default)]
+    pub customer_id: Option<AccountId>
+}
@@ -0,0 +1,97 @@
+use near_sdk::env;
+use near_sdk::AccountId;
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::serde_json;
+use crate::types::*;
+use crate::role::*;
+
+const EVENT_STANDARD: &str = "baggage_checkin";
+const EVENT_VERSION: &str = "1.0.0";
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data")]
+#[serde(rename_all = "snake_case")]
+pub enum Event<'a> {
+    OwnerProposed {
+        owner: &'a AccountId,
+        pending_owner: &'a AccountId
+    },
+    OwnerChanged {
+        old_owner: &'a AccountId,
+        new_owner: &'a AccountId
+    },
+    GuardianChanged {
+        guardian: Option<&'a AccountId>
+    },
+    Paused {
+        account_id: &'a AccountId
+    },
+    Unpaused {
+        account_id: &'a AccountId
+    },
+    AirlineAdded {
+        airline_id: AirlineId,
+        iata_code: &'a String,
+        payment_account: &'a AccountId
+    },
+    RoleGranted {
+        account_id: &'a AccountId,
+        role: Role,
+        airline_id: Option<AirlineId>
+    },
+    RoleRevoked {
+        account_id: &'a AccountId,
+        role: Role,
+        airline_id: Option<AirlineId>
+    },
+    PaymentAccountChanged {
+        airline_id: AirlineId,
+        old_payment_account: &'a AccountId,
+        new_payment_account: &'a AccountId
+    },
+    CommissionChanged {
+        old_commission: BasisPoints,
+        new_commission: BasisPoints
+    },
+    TreasuryChanged {
+        old_treasury: &'a AccountId,
+        new_treasury: &'a AccountId
+    },
+    AirlineWithdrawn {
+        airline_id: AirlineId,
+        payment_account: &'a AccountId,
+        token_id: Option<&'a AccountId>,
+        amount: U128
+    },
+    CommissionWithdrawn {
+        treasury: &'a AccountId,
+        token_id: Option<&'a AccountId>,
+        amount: U128
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a Event<'a>
+}
+
+impl Event<'_> {
+    /// Logs the event in the NEP-297 format
+    pub fn emit(&self) {
+        let log = EventLog {
+            standard: EVENT_STANDARD,
+            version: EVENT_VERSION,
+            event: self
+        };
+        env::log(format!(
+            "EVENT_JSON:{}",
+            serde_json::to_string(&log).unwrap()
+        ).as_bytes());
+    }
+}
@@ -1,34 +1,153 @@
 use near_sdk::Balance;
-use near_sdk::collections::{UnorderedMap};
+use near_sdk::json_types::U128;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};  
+use near_sdk::{serde::Serialize};
   
 use crate::baggage::*;
+use crate::surcharge::*;
+use crate::kind_rule::*;
+use crate::loyalty::*;
 use crate::types::*;
 
+/// The rules of the contract applied on top of the strategy of the class,
+/// `tier` is the one of the customer with the airline of the flight
+pub struct FeePolicy {
+    pub surcharge: Option<SurchargePolicy>,
+    pub kind_rules: Vec<(BaggageKind, KindRule)>,
+    pub tier: Option<(LoyaltyTier, TierBenefit)>
+}
+
+impl FeePolicy {
+    /// The rule set by the owner, else the default one of the kind
+    pub fn get_kind_rule(&self, kind: BaggageKind) -> KindRule {
+        self.kind_rules
+            .iter()
+            .find(|(rule_kind, _)| *rule_kind == kind)
+            .map(|(_, rule)| rule.clone())
+            .unwrap_or_else(|| KindRule::default_for(kind))
+    }
+
+    /// Free baggages never pay a surcharge
+    pub fn get_surcharge_policy(&self, baggage: &Baggage) -> Option<&SurchargePolicy> {
+        match self.get_kind_rule(*baggage.get_kind()).pricing {
+            KindPricing::Free => None,
+            _ => self.surcharge.as_ref()
+        }
+    }
+
+    fn is_priced_by_weight(&self, baggage: &Baggage) -> bool {
+        self.get_kind_rule(*baggage.get_kind()).pricing == KindPricing::Weight
+    }
+
+    /// The heaviest baggages priced by weight, as many as the tier gives for free
+    fn get_free_baggages(&self, baggages: &[Baggage]) -> Vec<BaggageId> {
+        let count = self.tier
+            .as_ref()
+            .map_or(0, |(_, benefit)| usize::from(benefit.free_baggages));
+        let mut priced: Vec<&Baggage> = baggages
+            .iter()
+            .filter(|baggage| self.is_priced_by_weight(baggage))
+            .collect();
+        priced.sort_by(|a, b| b.get_billed_weight().total_cmp(a.get_billed_weight()));
+        priced.iter().take(count).map(|baggage| *baggage.get_id()).collect()
+    }
+}
+
+/// The fee of a flight in USD, item by item
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Debug, PartialEq)]
+pub struct FeeQuote {
+    /// The strategy of the class, with the flat fees and surcharges
+    pub class_fee: U128,
+    pub tier: Option<LoyaltyTier>,
+    pub free_baggages: Vec<BaggageId>,
+    pub free_baggages_discount: U128,
+    pub tier_discount: U128,
+    pub total: U128
+}
+
+/// Applies the loyalty tier on top of the strategy of the class
+pub fn quote(
+    strategy: &dyn FeeStrategy,
+    distance: Distance,
+    members: usize,
+    baggages: &[Baggage],
+    policy: &FeePolicy
+) -> FeeQuote {
+    let class_fee = strategy.calculate_fee(distance, members, baggages, policy);
+    let free_baggages = policy.get_free_baggages(baggages);
+    let paid_baggages: Vec<Baggage> = baggages
+        .iter()
+        .filter(|baggage| !free_baggages.contains(baggage.get_id()))
+        .cloned()
+        .collect();
+    let paid_fee = strategy.calculate_fee(distance, members, &paid_baggages, policy);
+    let tier_discount = policy.tier
+        .as_ref()
+        .map_or(0, |(_, benefit)| apply_basis_points(paid_fee, benefit.discount));
+
+    FeeQuote {
+        class_fee: U128(class_fee),
+        tier: policy.tier.as_ref().map(|(tier, _)| *tier),
+        free_baggages,
+        free_baggages_discount: U128(class_fee.saturating_sub(paid_fee)),
+        tier_discount: U128(tier_discount),
+        total: U128(paid_fee - tier_discount)
+    }
+}
+
+/// `members` is the number of customers sharing the baggages, 1 out of a group
 pub trait FeeStrategy {
     fn calculate_fee(
         &self,
         distance: Distance,
-        baggages: &UnorderedMap<BaggageId, Baggage>
+        members: usize,
+        baggages: &[Baggage],
+        policy: &FeePolicy
     ) -> Balance;
 }
 
-fn sum_weight(baggages: &UnorderedMap<BaggageId, Baggage>) -> Weight{
+/// Only the baggages priced by weight are counted
+fn sum_weight(baggages: &[Baggage], policy: &FeePolicy) -> Weight{
     let mut ret: Weight = 0.0;
-    for baggage in baggages.values() {
-        ret += baggage.get_weight();
+    for baggage in baggages {
+        if policy.is_priced_by_weight(baggage) {
+            ret += baggage.get_billed_weight();
+        }
     }
     ret
 }
 
-fn max_weight(baggages: &UnorderedMap<BaggageId, Baggage>) -> Weight{
-    let mut ret: Weight = 0.0;
-    for baggage in baggages.values() {
-        if *(baggage.get_weight()) > ret {
-            ret = *(baggage.get_weight());
-        }
-    }
-    ret
+/// The flat fees and the surcharges
+fn sum_extra(
+    baggages: &[Baggage],
+    policy: &FeePolicy
+) -> Balance {
+    baggages
+        .iter()
+        .map(|baggage| {
+            let flat = match policy.get_kind_rule(*baggage.get_kind()).pricing {
+                KindPricing::Flat(fee) => fee.0,
+                _ => 0
+            };
+            let surcharge = policy
+                .get_surcharge_policy(baggage)
+                .map_or(0, |surcharge| surcharge.get_surcharge(baggage));
+            flat + surcharge
+        })
+        .sum()
+}
+
+/// The weight of the `count` heaviest baggages
+fn max_weight(baggages: &[Baggage], count: usize, policy: &FeePolicy) -> Weight{
+    let mut weights: Vec<Weight> = baggages
+        .iter()
+        .filter(|baggage| policy.is_priced_by_weight(baggage))
+        .map(|baggage| *baggage.get_billed_weight())
+        .collect();
+    weights.sort_by(|a, b| b.total_cmp(a));
+    weights.iter().take(count).sum()
 }
 
 #[derive(BorshDeserialize, BorshSerialize)]
@@ -37,9 +156,13 @@ impl FeeStrategy for FirstFee {
     fn calculate_fee(
         &self,
         _distance: Distance,
-        baggages: &UnorderedMap<BaggageId, Baggage>
+        members: usize,
+        baggages: &[Baggage],
+        policy: &FeePolicy
     ) -> Balance {
-        ((sum_weight(baggages)-max_weight(baggages)) as Balance)*2
+        // The heaviest baggage of each member is free
+        ((sum_weight(baggages, policy)-max_weight(baggages, members, policy)) as Balance)*2
+            + sum_extra(baggages, policy)
     }
 }
 
@@ -49,9 +172,12 @@ impl FeeStrategy for BusinessFee {
     fn calculate_fee(
         &self,
         _distance: Distance,
-        baggages: &UnorderedMap<BaggageId, Baggage>
+        _members: usize,
+        baggages: &[Baggage],
+        policy: &FeePolicy
     ) -> Balance {
-        (sum_weight(baggages) as Balance)*2
+        (sum_weight(baggages, policy) as Balance)*2
+            + sum_extra(baggages, policy)
     }
 }
 
@@ -61,8 +187,12 @@ impl FeeStrategy for EconomyFee {
     fn calculate_fee(
         &self,
         distance: Distance,
-        baggages:&UnorderedMap<BaggageId, Baggage>
+        members: usize,
+        baggages: &[Baggage],
+        policy: &FeePolicy
     ) -> Balance {
-        ((sum_weight(baggages)+distance) as Balance)*2
+        // The distance is paid by each member
+        ((sum_weight(baggages, policy)+distance*members as Distance) as Balance)*2
+            + sum_extra(baggages, policy)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,40 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};  
+use near_sdk::{serde::{Serialize, Deserialize}};
+use crate::types::*;
+
+/// A flight operated by an airline, shared by all of its customers
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Flight {
+    flight_id: FlightId,
+    airline_id: AirlineId,
+    /// The IATA codes of the origin and destination airports
+    route: Option<(String, String)>
+}
+
+impl Flight {
+    pub fn new(flight_id: FlightId, airline_id: AirlineId) -> Self {
+        Self {
+            flight_id,
+            airline_id,
+            route: None
+        }
+    }
+
+    pub fn get_id(&self) -> &FlightId {
+        &self.flight_id
+    }
+
+    pub fn get_airline_id(&self) -> &AirlineId {
+        &self.airline_id
+    }
+
+    pub fn get_route(&self) -> &Option<(String, String)> {
+        &self.route
+    }
+
+    pub fn set_route(&mut self, origin: String, destination: String) {
+        self.route = Some((origin, destination));
+    }
+}
@@ -1,4 +1,4 @@
-use near_sdk::Balance;
+use near_sdk::{env, AccountId, Balance, CryptoHash};
 use near_sdk::collections::UnorderedMap;
 use near_sdk::{serde::{Serialize, Deserialize}};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};  
@@ -7,10 +7,14 @@ use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use crate::fee::*;
 use crate::types::*;
 use crate::baggage::*;
+use crate::escrow::*;
+use crate::dangerous_goods::*;
+use crate::segment::*;
+use crate::delegation::*;
 
 #[derive(Serialize, Deserialize, BorshDeserialize, BorshSerialize)]
 #[serde(crate = "near_sdk::serde")]
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum FlightClass {
     First,
     Business,
@@ -19,12 +23,14 @@ pub enum FlightClass {
 
 #[derive(Serialize, Deserialize, BorshDeserialize, BorshSerialize)]
 #[serde(crate = "near_sdk::serde")]
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum FlightState {
     Idle,
     Checked,
+    AwaitingTopUp,
     Delivered,
-    Claimed
+    Claimed,
+    Refunded
 }
 
 #[derive(BorshDeserialize, BorshSerialize)]
@@ -33,21 +39,52 @@ pub struct FlightDetail {
     flight_class: FlightClass, 
     distance: Distance,
     baggages: UnorderedMap<BaggageId, Baggage>,
-    state: FlightState
+    state: FlightState,
+    escrow: Option<Escrow>,
+    segments: Vec<Segment>,
+    group_id: Option<GroupId>,
+    delegations: Vec<Delegation>,
+    /// The account the customer allowed to pay for the flight
+    sponsor: Option<AccountId>,
+    /// The SHA-256 of the code printed on the baggage receipt
+    claim_code_hash: Option<CryptoHash>
 }
 
 impl FlightDetail {
     pub fn new(
+        customer_id: &AccountId,
         flight_id: FlightId,
         flight_class: FlightClass,
         distance: Distance,
     ) -> Self {
+        Self::new_itinerary(
+            customer_id,
+            flight_class,
+            vec![Segment::new(flight_id, distance)]
+        )
+    }
+
+    /// A flight with connections, identified by its first segment.
+    /// The distance is the one of the whole itinerary
+    pub fn new_itinerary(
+        customer_id: &AccountId,
+        flight_class: FlightClass,
+        segments: Vec<Segment>
+    ) -> Self {
+        let flight_id = segments[0].flight_id;
         Self {
             flight_id,
             flight_class,
-            distance,
-            baggages: UnorderedMap::new(format!("baggages_{}",flight_id).as_bytes()),
-            state: FlightState::Idle
+            distance: segments.iter().map(|segment| segment.distance).sum(),
+            // Many customers share a flight, so the prefix needs both
+            baggages: UnorderedMap::new(format!("baggages_{}_{}",customer_id,flight_id).as_bytes()),
+            state: FlightState::Idle,
+            escrow: None,
+            segments,
+            group_id: None,
+            delegations: Vec::new(),
+            sponsor: None,
+            claim_code_hash: None
         }
     }
 
@@ -71,6 +108,14 @@ impl FlightDetail {
         &self.state
     }
 
+    pub fn set_escrow(&mut self, escrow: Escrow) {
+        self.escrow = Some(escrow);
+    }
+
+    pub fn get_escrow(&self) -> &Option<Escrow> {
+        &self.escrow
+    }
+
     pub fn get_flight_id(&mut self) -> &FlightId{
         &self.flight_id
     }
@@ -83,14 +128,161 @@ impl FlightDetail {
         &self.distance
     }
     
+    pub fn get_segments(&self) -> &Vec<Segment> {
+        &self.segments
+    }
+
+    /// The segment whose baggages are not transferred yet,
+    /// the last one is delivered instead
+    pub fn get_current_segment(&self) -> &Segment {
+        self.segments
+            .iter()
+            .find(|segment| !segment.transferred)
+            .unwrap_or_else(|| self.segments.last().unwrap())
+    }
+
+    pub fn is_last_segment(&self, flight_id: FlightId) -> bool {
+        self.segments.last().unwrap().flight_id == flight_id
+    }
+
+    /// The first segment left once the baggages were transferred from it
+    pub fn has_departed(&self) -> bool {
+        self.segments.iter().any(|segment| segment.transferred)
+    }
+
+    /// Marks the baggages as transferred from the current segment to the next one
+    pub fn transfer(&mut self) {
+        let segment = self.segments
+            .iter_mut()
+            .find(|segment| !segment.transferred)
+            .unwrap();
+        segment.transferred = true;
+    }
+
+    pub fn get_group_id(&self) -> &Option<GroupId> {
+        &self.group_id
+    }
+
+    pub fn set_group_id(&mut self, group_id: Option<GroupId>) {
+        self.group_id = group_id;
+    }
+
+    pub fn get_delegations(&self) -> &Vec<Delegation> {
+        &self.delegations
+    }
+
+    pub fn get_delegation(&self, delegate: &AccountId) -> Option<&Delegation> {
+        self.delegations
+            .iter()
+            .find(|delegation| delegation.delegate == *delegate)
+    }
+
+    /// Replaces the delegation of the same delegate
+    pub fn authorize(&mut self, delegation: Delegation) {
+        self.revoke(&delegation.delegate);
+        assert!(
+            self.delegations.len() < MAX_DELEGATIONS,
+            "You cannot authorize more than {} delegates", MAX_DELEGATIONS
+        );
+        self.delegations.push(delegation);
+    }
+
+    /// Tells if the delegate was authorized
+    pub fn revoke(&mut self, delegate: &AccountId) -> bool {
+        let len = self.delegations.len();
+        self.delegations.retain(|delegation| delegation.delegate != *delegate);
+        self.delegations.len() < len
+    }
+
+    /// Claims the baggages not claimed yet which the delegation covers,
+    /// all of them without delegation. Returns their ids
+    pub fn claim_baggages(&mut self, delegation: Option<&Delegation>) -> Vec<BaggageId> {
+        let mut claimed = Vec::new();
+        for mut baggage in self.baggages.values_as_vector().to_vec() {
+            if baggage.is_claimed()
+                || delegation.is_some_and(|delegation| !delegation.covers(*baggage.get_id()))
+            {
+                continue;
+            }
+            baggage.claim();
+            claimed.push(*baggage.get_id());
+            self.baggages.insert(baggage.get_id(), &baggage);
+        }
+        claimed
+    }
+
+    pub fn get_sponsor(&self) -> &Option<AccountId> {
+        &self.sponsor
+    }
+
+    pub fn set_sponsor(&mut self, sponsor: Option<AccountId>) {
+        self.sponsor = sponsor;
+    }
+
+    pub fn get_claim_code_hash(&self) -> &Option<CryptoHash> {
+        &self.claim_code_hash
+    }
+
+    pub fn set_claim_code_hash(&mut self, claim_code_hash: Option<CryptoHash>) {
+        self.claim_code_hash = claim_code_hash;
+    }
+
+    /// The code given at the carousel must match the hash set at the delivery
+    pub fn assert_claim_code(&self, claim_code: &str) {
+        let claim_code_hash = self.claim_code_hash.expect("The baggages have no claim code");
+        assert!(
+            env::sha256(claim_code.as_bytes()) == claim_code_hash,
+            "The claim code is wrong"
+        );
+    }
+
+    pub fn is_claimed(&self) -> bool {
+        self.baggages.values().all(|baggage| baggage.is_claimed())
+    }
+
     pub fn get_baggages(&self) -> &UnorderedMap<BaggageId, Baggage>{
         &self.baggages
     }
 
-    pub fn get_fee(&self) -> Balance {
-        self.get_fee_strategy().calculate_fee(
+    /// The first baggage waiting for its dangerous goods inspection
+    pub fn get_pending_inspection(&self) -> Option<BaggageId> {
+        self.baggages
+            .values()
+            .find(|baggage| *baggage.get_inspection() == InspectionState::Pending)
+            .map(|baggage| *baggage.get_id())
+    }
+
+    pub fn count_kind(&self, kind: BaggageKind) -> u64 {
+        self.baggages
+            .values()
+            .filter(|baggage| *baggage.get_kind() == kind)
+            .count() as u64
+    }
+
+    pub fn get_total_weight(&self) -> Weight {
+        self.baggages
+            .values()
+            .map(|baggage| *baggage.get_billed_weight())
+            .sum()
+    }
+
+    /// The fee in USD, with the flat fees, surcharges and loyalty tier of the policy
+    pub fn get_fee(&self, policy: &FeePolicy) -> Balance {
+        self.get_quote(policy).total.0
+    }
+
+    pub fn get_quote(&self, policy: &FeePolicy) -> FeeQuote {
+        self.get_pooled_quote(&self.baggages.values_as_vector().to_vec(), 1, policy)
+    }
+
+    /// The fee of the baggages of a group, in the class of this flight
+    pub fn get_pooled_quote(&self, baggages: &[Baggage], members: usize, policy: &FeePolicy) -> FeeQuote {
+        quote(
+            self.get_fee_strategy().as_ref(),
             self.distance,
-            &self.baggages
+            members,
+            baggages,
+            policy
         )
     }
 
@@ -0,0 +1,91 @@
+use near_sdk::AccountId;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{serde::{Serialize, Deserialize}};
+use crate::types::*;
+
+/// The customers a group can have, its leader included
+pub const MAX_GROUP_MEMBERS: usize = 9;
+
+/// Customers of the same flight and class pooling their allowance,
+/// the leader pays for the baggages of everyone
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Group {
+    group_id: GroupId,
+    flight_id: FlightId,
+    leader: AccountId,
+    /// The leader is the first member
+    members: Vec<AccountId>,
+    invited: Vec<AccountId>
+}
+
+impl Group {
+    pub fn new(group_id: GroupId, flight_id: FlightId, leader: AccountId) -> Self {
+        Self {
+            group_id,
+            flight_id,
+            members: vec![leader.clone()],
+            leader,
+            invited: Vec::new()
+        }
+    }
+
+    pub fn get_id(&self) -> &GroupId {
+        &self.group_id
+    }
+
+    pub fn get_flight_id(&self) -> &FlightId {
+        &self.flight_id
+    }
+
+    pub fn get_leader(&self) -> &AccountId {
+        &self.leader
+    }
+
+    pub fn get_members(&self) -> &Vec<AccountId> {
+        &self.members
+    }
+
+    pub fn is_leader(&self, account_id: &AccountId) -> bool {
+        self.leader == *account_id
+    }
+
+    /// The customers of the flight of each member
+    pub fn get_keys(&self) -> Vec<(AccountId, FlightId)> {
+        self.members
+            .iter()
+            .map(|member| (member.clone(), self.flight_id))
+            .collect()
+    }
+
+    pub fn invite(&mut self, account_id: AccountId) {
+        assert!(
+            !self.members.contains(&account_id) && !self.invited.contains(&account_id),
+            "{} is already in the group", account_id
+        );
+        assert!(
+            self.members.len() + self.invited.len() < MAX_GROUP_MEMBERS,
+            "A group cannot have more than {} members", MAX_GROUP_MEMBERS
+        );
+        self.invited.push(account_id);
+    }
+
+    pub fn join(&mut self, account_id: AccountId) {
+        let index = self.invited
+            .iter()
+            .position(|invited| *invited == account_id)
+            .expect("You are not invited to this group");
+        self.invited.remove(index);
+        self.members.push(account_id);
+    }
+
+    pub fn leave(&mut self, account_id: &AccountId) {
+        assert!(
+            !self.is_leader(account_id),
+            "The leader cannot leave the group while it has members"
+        );
+        self.members.retain(|member| member != account_id);
+    }
+}
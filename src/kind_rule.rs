@@ -0,0 +1,76 @@
+use near_sdk::json_types::U128;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{serde::{Serialize, Deserialize}};
+use crate::baggage::*;
+use crate::types::*;
+
+/// How the baggages of a kind are priced, the flat fee is in USD
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum KindPricing {
+    /// Priced by the strategy of the flight class
+    Weight,
+    Flat(U128),
+    Free
+}
+
+/// The pricing and limits of a baggage kind,
+/// on top of the allowance of the flight
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct KindRule {
+    pub pricing: KindPricing,
+    pub max_baggages: Option<u8>,
+    pub max_weight: Option<Weight>
+}
+
+impl KindRule {
+    /// Mobility aids are free, a pet travels alone in its cage for a flat fee
+    pub fn default_for(kind: BaggageKind) -> Self {
+        match kind {
+            BaggageKind::Pet => Self {
+                pricing: KindPricing::Flat(U128(50)),
+                max_baggages: Some(1),
+                max_weight: None
+            },
+            BaggageKind::MobilityAid => Self {
+                pricing: KindPricing::Free,
+                max_baggages: None,
+                max_weight: None
+            },
+            _ => Self {
+                pricing: KindPricing::Weight,
+                max_baggages: None,
+                max_weight: None
+            }
+        }
+    }
+
+    pub fn assert_valid(&self) {
+        if let Some(max_weight) = self.max_weight {
+            assert!(
+                max_weight > 0.0,
+                "Weight limits must be positive"
+            );
+        }
+    }
+
+    /// Checks a new baggage of the kind, `baggage_len` is the number
+    /// of baggages of the kind already checked
+    pub fn assert_baggage(&self, kind: BaggageKind, baggage_len: u64, baggage_weight: Weight) {
+        if let Some(max_baggages) = self.max_baggages {
+            if baggage_len >= u64::from(max_baggages) {
+                panic!("You cannot add more than {} baggages of kind {:?}", max_baggages, kind);
+            }
+        }
+        if let Some(max_weight) = self.max_weight {
+            if baggage_weight > max_weight {
+                panic!("A baggage of kind {:?} cannot weigh more than {} lbs", kind, max_weight);
+            }
+        }
+    }
+}
@@ -1,5 +1,10 @@
+// The calls generated by `ext_contract` also take the receiver, deposit and gas
+#![allow(clippy::too_many_arguments)]
+
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::{env, near_bindgen, Promise, Balance};
+use near_sdk::{env, near_bindgen, ext_contract, assert_one_yocto, serde_json};
+use near_sdk::{Promise, PromiseOrValue, PromiseResult, Balance, Gas, PanicOnDefault, StorageUsage};
+use near_sdk::json_types::{U64, U128, Base58CryptoHash};
 use near_sdk::{AccountId};
 use near_sdk::collections::{UnorderedMap};
 
@@ -7,40 +12,165 @@ pub mod flight_detail;
 pub mod baggage;
 pub mod types;
 pub mod fee;
+pub mod events;
+pub mod airline;
+pub mod flight;
+pub mod role;
+pub mod storage;
+pub mod escrow;
+pub mod oracle;
+pub mod settlement;
+pub mod allowance;
+pub mod surcharge;
+pub mod kind_rule;
+pub mod dangerous_goods;
+pub mod segment;
+pub mod airport;
+pub mod group;
+pub mod delegation;
+pub mod loyalty;
 
 use crate::flight_detail::*;
 use crate::baggage::*;
 use crate::types::*;
+use crate::events::*;
+use crate::airline::*;
+use crate::flight::*;
+use crate::role::*;
+use crate::storage::*;
+use crate::escrow::*;
+use crate::oracle::*;
+use crate::settlement::*;
+use crate::allowance::*;
+use crate::surcharge::*;
+use crate::kind_rule::*;
+use crate::dangerous_goods::*;
+use crate::segment::*;
+use crate::airport::*;
+use crate::group::*;
+use crate::delegation::*;
+use crate::loyalty::*;
+use crate::fee::*;
 
 near_sdk::setup_alloc!();
 
+const GAS_FOR_MIGRATE: Gas = 50_000_000_000_000;
+const GAS_FOR_FT_TRANSFER: Gas = 10_000_000_000_000;
+const GAS_FOR_PRICE_DATA: Gas = 10_000_000_000_000;
+const GAS_FOR_ACCEPT_CALLBACK: Gas = 20_000_000_000_000;
+const GAS_FOR_PAYOUT_CALLBACK: Gas = 10_000_000_000_000;
+
+#[ext_contract(ext_ft)]
+pub trait FungibleToken {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
+#[ext_contract(ext_oracle)]
+pub trait PriceOracleContract {
+    fn get_price_data(&self, asset_ids: Option<Vec<String>>) -> PriceData;
+}
+
+#[ext_contract(ext_self)]
+pub trait SelfCallbacks {
+    fn on_accept_price(
+        &mut self,
+        customer_id: AccountId,
+        payer: AccountId,
+        flight_id: FlightId,
+        fee: U128,
+        deposit: U128
+    ) -> bool;
+    fn on_withdraw(&mut self, airline_id: AirlineId, token_id: Option<AccountId>, amount: U128) -> bool;
+    fn on_withdraw_commission(&mut self, token_id: Option<AccountId>, amount: U128) -> bool;
+    fn on_refund(&mut self, account_id: AccountId, token_id: Option<AccountId>, amount: U128) -> bool;
+}
+
 #[near_bindgen]
-#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
     owner: AccountId,
-    payment_account: AccountId,
-    initialized: bool,
+    pending_owner: Option<AccountId>,
+    guardian: Option<AccountId>,
+    paused: bool,
+    airlines: UnorderedMap<AirlineId, Airline>,
+    flights: UnorderedMap<FlightId, Flight>,
+    roles: UnorderedMap<AccountId, Vec<RoleGrant>>,
+    accepted_tokens: UnorderedMap<AccountId, u8>,
+    price_oracle: Option<PriceOracle>,
+    commission: BasisPoints,
+    treasury: AccountId,
+    commissions: UnorderedMap<Option<AccountId>, Balance>,
+    airline_balances: UnorderedMap<(AirlineId, Option<AccountId>), Balance>,
+    settlements: UnorderedMap<(AirlineId, SettlementPeriod), Vec<Settlement>>,
+    owed: UnorderedMap<(AccountId, Option<AccountId>), Balance>,
+    class_allowances: UnorderedMap<FlightClass, Allowance>,
+    flight_allowances: UnorderedMap<(FlightId, FlightClass), Allowance>,
+    surcharge_policies: UnorderedMap<FlightClass, SurchargePolicy>,
+    kind_rules: UnorderedMap<BaggageKind, KindRule>,
+    airports: UnorderedMap<String, Airport>,
+    groups: UnorderedMap<GroupId, Group>,
+    loyalty_tiers: UnorderedMap<(AirlineId, AccountId), LoyaltyTier>,
+    tier_benefits: UnorderedMap<(AirlineId, LoyaltyTier), TierBenefit>,
+    storage_accounts: UnorderedMap<AccountId, StorageAccount>,
+    account_storage_usage: StorageUsage,
     user_flights: UnorderedMap<(AccountId, FlightId), FlightDetail>,
-    count_baggage: BaggageId
+    count_airline: AirlineId,
+    count_baggage: BaggageId,
+    count_group: GroupId
 }
 
-impl Default for Contract {
-    fn default() -> Self {
-        Self {
-            owner: env::signer_account_id(),
-            payment_account: env::predecessor_account_id(),
-            initialized: false,
+#[near_bindgen]
+impl Contract {
+    /// Only the contract account can call it, so nobody can initialize it before the deployer
+    #[init]
+    #[private]
+    pub fn new(owner: AccountId) -> Self {
+        assert!(
+            env::is_valid_account_id(owner.as_bytes()),
+            "Owner account is invalid"
+        );
+
+        env::log(format!(
+            "Contract initialized by {}",
+            owner
+        ).as_bytes());
+
+        let mut contract = Self {
+            treasury: owner.clone(),
+            owner,
+            pending_owner: None,
+            guardian: None,
+            paused: false,
+            airlines: UnorderedMap::new(b"airlines".to_vec()),
+            flights: UnorderedMap::new(b"flights".to_vec()),
+            roles: UnorderedMap::new(b"roles".to_vec()),
+            accepted_tokens: UnorderedMap::new(b"accepted_tokens".to_vec()),
+            price_oracle: None,
+            commission: 0,
+            commissions: UnorderedMap::new(b"commissions".to_vec()),
+            airline_balances: UnorderedMap::new(b"airline_balances".to_vec()),
+            settlements: UnorderedMap::new(b"settlements".to_vec()),
+            owed: UnorderedMap::new(b"owed".to_vec()),
+            class_allowances: UnorderedMap::new(b"class_allowances".to_vec()),
+            flight_allowances: UnorderedMap::new(b"flight_allowances".to_vec()),
+            surcharge_policies: UnorderedMap::new(b"surcharge_policies".to_vec()),
+            kind_rules: UnorderedMap::new(b"kind_rules".to_vec()),
+            airports: UnorderedMap::new(b"airports".to_vec()),
+            groups: UnorderedMap::new(b"groups".to_vec()),
+            loyalty_tiers: UnorderedMap::new(b"loyalty_tiers".to_vec()),
+            tier_benefits: UnorderedMap::new(b"tier_benefits".to_vec()),
+            storage_accounts: UnorderedMap::new(b"storage_accounts".to_vec()),
+            account_storage_usage: 0,
             user_flights: UnorderedMap::new(b"user_flights".to_vec()),
-            count_baggage: 0
-        }
+            count_airline: 0,
+            count_baggage: 0,
+            count_group: 0
+        };
+        contract.measure_account_storage_usage();
+        contract
     }
-}
 
-#[near_bindgen]
-impl Contract {
     fn _debug(&self) {
-        self.assert_initialized();
-
         let signer = env::signer_account_id();
         let predecessor = env::predecessor_account_id();
         let current = env::current_account_id();
@@ -51,310 +181,1556 @@ impl Contract {
     }
 
     // ====================================================================
-    pub fn get_payment_account(&self) -> &String {
-        self.assert_initialized();
-        &self.payment_account
+    pub fn get_owner(&self) -> &String {
+        &self.owner
     }
 
-    pub fn get_initialized(&self) -> bool {
-        self.initialized
+    pub fn get_pending_owner(&self) -> Option<AccountId> {
+        self.pending_owner.clone()
     }
 
-    pub fn get_owner(&self) -> &String {
-        &self.owner
+    pub fn get_guardian(&self) -> Option<AccountId> {
+        self.guardian.clone()
     }
-    // ====================================================================
-    pub fn init(&mut self, payment_account: AccountId) {
-        // Contract must not be initialized
-        assert_eq!(
-            self.initialized,
-            false,
-            "Contract was already initialized"
-        );
 
-        // Only owner can init
-        let predecessor = env::predecessor_account_id(); 
-        assert_eq!(
-            self.owner,
-            predecessor,
-            "Only contract owner can init"
-        );
-        
-        // Payment account (Airlines) must not same as contract account
-        let current = env::current_account_id();
-        assert_ne!(
-            payment_account,
-            current,
-            "Payment account cannot be contract"
-        );
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
 
-        self.initialized = true;
-        self.user_flights = UnorderedMap::new(b"user_flights".to_vec());
-        self.payment_account = payment_account;
-    
-        env::log(format!(
-            "Contract initialized by {}, payment to {}",
-            predecessor,
-            self.payment_account
-        ).as_bytes());
+    pub fn get_airline(&self, airline_id: AirlineId) -> Airline {
+        self.airlines.get(&airline_id).expect("Cannot find the airline")
     }
 
-    pub fn reset(&mut self) {
-        self.assert_initialized();
+    pub fn get_airlines(&self) -> Vec<Airline> {
+        self.airlines.values().collect()
+    }
 
-        let predecessor = env::predecessor_account_id();
-        assert_eq!(
-            self.owner,
-            predecessor,
-            "Only the contract owner can reset"
-        );
+    pub fn get_flight(&self, flight_id: FlightId) -> Flight {
+        self.flights.get(&flight_id).expect("Cannot find the flight")
+    }
 
-        self.initialized = false;
-        self.payment_account = self.owner.clone();
-        self.user_flights.clear();
-    
-        env::log("Reset successfully".as_bytes());
+    /// The allowance set for the flight, else the one of the class
+    pub fn get_allowance(&self, flight_id: FlightId, flight_class: FlightClass) -> Allowance {
+        self.flight_allowances
+            .get(&(flight_id, flight_class))
+            .or_else(|| self.class_allowances.get(&flight_class))
+            .unwrap_or_default()
     }
 
-    // ====================================================================
-    pub fn registry(
-        &mut self, 
-        flight_id: FlightId,
-        flight_class: FlightClass,
-        distance: Distance
-    ) {
-        self.assert_initialized();
+    pub fn get_surcharge_policy(&self, flight_class: FlightClass) -> Option<SurchargePolicy> {
+        self.surcharge_policies.get(&flight_class)
+    }
 
-        let customer = env::predecessor_account_id();
-        let key = (customer, flight_id);
-        match self.user_flights.get(&key) {
-            Some(_flight) => {
-                panic!("This flight was registered by you");
-            },
-            None => {
-                let new_flight = FlightDetail::new(
-                    flight_id,
-                    flight_class,
-                    distance
-                );
-                self.user_flights.insert(&key, &new_flight);
-                env::log("Registry successfully".as_bytes());
-            }
-        }
+    pub fn get_kind_rule(&self, kind: BaggageKind) -> KindRule {
+        FeePolicy {
+            surcharge: None,
+            kind_rules: self.kind_rules.to_vec(),
+            tier: None
+        }.get_kind_rule(kind)
     }
 
-    pub fn add_baggage(
-        &mut self, 
-        flight_id: FlightId, 
-        baggage_weight: Weight
-    ) -> BaggageId {
-        self.assert_initialized();
-    
-        let customer_id = env::predecessor_account_id();
-        let key = &(customer_id, flight_id);
+    pub fn get_airport(&self, code: String) -> Option<Airport> {
+        self.airports.get(&code)
+    }
 
-        match self.user_flights.get(&key) {
-            Some(mut flight) => {
-                self.assert_state(&flight, FlightState::Idle);
-                        
-                // Each (account, flight) can only have at most 3
-                let baggage_len: usize = flight.get_baggages().len() as usize;
-                if baggage_len>=3 {
-                    panic!("You cannot add more than 3 baggages"); 
-                } else {
-                    let baggage_id: BaggageId = self.count_baggage;
-                    let new_baggage = Baggage::new (
-                        baggage_id,
-                        baggage_weight
-                    );
+    /// The great-circle distance between two airports, in miles
+    pub fn get_distance(&self, origin: String, destination: String) -> Distance {
+        let origin = self.airports
+            .get(&origin)
+            .unwrap_or_else(|| panic!("Cannot find the airport {}", origin));
+        let destination = self.airports
+            .get(&destination)
+            .unwrap_or_else(|| panic!("Cannot find the airport {}", destination));
+        origin.get_distance(&destination)
+    }
 
-                    flight.add_baggage(
-                        new_baggage
-                    );
-                    self.user_flights.insert(&key,&flight);
-                    self.count_baggage += 1;
+    pub fn get_group(&self, group_id: GroupId) -> Option<Group> {
+        self.groups.get(&group_id)
+    }
 
-                    env::log("Add baggage succesfully".as_bytes());
-                    env::log(format!("Baggage id: {}",&baggage_id).as_bytes());
+    pub fn get_loyalty_tier(&self, airline_id: AirlineId, account_id: AccountId) -> Option<LoyaltyTier> {
+        self.loyalty_tiers.get(&(airline_id, account_id))
+    }
 
-                    baggage_id
-                }
-            },
-            None => {
-                panic!("Cannot find your flight");
-            }
-        }
+    /// The benefit set by the airline, else the default one of the tier
+    pub fn get_tier_benefit(&self, airline_id: AirlineId, tier: LoyaltyTier) -> TierBenefit {
+        self.tier_benefits
+            .get(&(airline_id, tier))
+            .unwrap_or_else(|| TierBenefit::default_for(tier))
     }
 
-    pub fn check_number_baggages(
-        &mut self, 
-        flight_id: FlightId
-    ) -> u64 {
-        self.assert_initialized();
+    /// The accounts allowed to claim the baggages of the customer
+    pub fn get_pickups(&self, customer_id: AccountId, flight_id: FlightId) -> Vec<Delegation> {
+        self.user_flights
+            .get(&(customer_id, flight_id))
+            .expect("Cannot find the flight of the customer")
+            .get_delegations()
+            .clone()
+    }
 
-        let customer_id = env::predecessor_account_id();
-        let key = &(customer_id, flight_id);
-        match self.user_flights.get(&key) {
-            Some(flight) => {
-                let baggages = flight.get_baggages();
-                env::log(format!("Number of baggages: {}",baggages.len()).as_bytes());
-                baggages.len()
-            },
-            None => {
-                panic!("Cannot find your flight");
-            }
-        }
+    pub fn get_price_oracle(&self) -> &Option<PriceOracle> {
+        &self.price_oracle
     }
 
-    pub fn check_baggage(
-        &mut self, 
-        flight_id: FlightId,
-        baggage_id: BaggageId
-    ) -> Baggage {
-        self.assert_initialized();
+    pub fn get_tokens(&self) -> Vec<(AccountId, u8)> {
+        self.accepted_tokens.to_vec()
+    }
 
-        let customer_id = env::predecessor_account_id();
-        let key = &(customer_id, flight_id);
+    pub fn get_commission(&self) -> BasisPoints {
+        self.commission
+    }
 
-        match self.user_flights.get(&key) {
-            Some(flight) => {
-                let baggages = flight.get_baggages();
-                
-                match baggages.get(&baggage_id) {
-                    Some(baggage) => {
-                        env::log(format!(
-                            "[{}] Weight={}", 
-                            baggage.get_id(), 
-                            baggage.get_weight()
-                        ).as_bytes());
-                        baggage
-                    },
-                    None => {
-                        panic!("Cannot find your baggage");
-                    }
-                }
-            },
-            None => {
-                panic!("Cannot find your flight");
-            }
-        }
+    pub fn get_treasury(&self) -> &AccountId {
+        &self.treasury
     }
 
-    pub fn check_fee(&mut self, flight_id: FlightId) -> Balance {
-        self.assert_initialized();
-        
-        let customer_id = env::predecessor_account_id();
-        let key = &(customer_id, flight_id);
+    /// The commissions not withdrawn yet, `None` is NEAR
+    pub fn get_commissions(&self) -> Vec<(Option<AccountId>, U128)> {
+        self.commissions
+            .iter()
+            .map(|(token_id, amount)| (token_id, U128(amount)))
+            .collect()
+    }
 
-        match self.user_flights.get(&key) {
-            Some(flight) => {        
-                let price = flight.get_fee();
-                env::log(format!("Your price: {} NEAR",&price).as_bytes());
-                price
-            },
-            None => {
-                panic!("Cannot find your flight");
-            }
-        }
+    /// The earnings the airline can withdraw, `None` is NEAR
+    pub fn get_airline_balances(&self, airline_id: AirlineId) -> Vec<(Option<AccountId>, U128)> {
+        self.airline_balances
+            .iter()
+            .filter(|((id, _), _)| *id == airline_id)
+            .map(|((_, token_id), amount)| (token_id, U128(amount)))
+            .collect()
     }
 
-    pub fn check_state(&mut self, flight_id: FlightId) -> String {
-        self.assert_initialized();
-        
-        let customer_id = env::predecessor_account_id();
-        let key = &(customer_id, flight_id);
+    /// The claims of the airline grouped by period (days since the Unix epoch)
+    /// and token, from `from_period` to `to_period` included
+    pub fn get_settlement_report(
+        &self,
+        airline_id: AirlineId,
+        from_period: SettlementPeriod,
+        to_period: SettlementPeriod
+    ) -> Vec<Settlement> {
+        assert!(
+            from_period <= to_period,
+            "The report must start before it ends"
+        );
+        assert!(
+            to_period - from_period < MAX_SETTLEMENT_PERIODS,
+            "The report cannot cover more than {} periods", MAX_SETTLEMENT_PERIODS
+        );
 
-        match self.user_flights.get(&key) {
-            Some(flight) => {        
-                env::log(format!("State: {:?}",flight.get_state()).as_bytes());
-                format!("{:?}",&flight.get_state())
-            },
-            None => {
-                panic!("Cannot find your flight");
-            }
+        (from_period..=to_period)
+            .filter_map(|period| self.settlements.get(&(airline_id, period)))
+            .flatten()
+            .collect()
+    }
+
+    /// The refunds which failed and can be withdrawn again, `None` is NEAR
+    pub fn get_owed(&self, account_id: AccountId) -> Vec<(Option<AccountId>, U128)> {
+        self.owed
+            .iter()
+            .filter(|((id, _), _)| *id == account_id)
+            .map(|((_, token_id), amount)| (token_id, U128(amount)))
+            .collect()
+    }
+
+    pub fn get_roles(&self, account_id: AccountId) -> Vec<RoleGrant> {
+        let mut grants = self.roles.get(&account_id).unwrap_or_default();
+        if account_id == self.owner {
+            grants.insert(0, RoleGrant::new(Role::Owner, None));
         }
+        grants
     }
 
-    
-    pub fn check_class(&mut self, flight_id: FlightId) -> String {
-        self.assert_initialized();
-        
-        let customer_id = env::predecessor_account_id();
-        let key = &(customer_id, flight_id);
+    pub fn has_role(
+        &self,
+        account_id: AccountId,
+        role: Role,
+        airline_id: Option<AirlineId>
+    ) -> bool {
+        self.get_roles(account_id).contains(&RoleGrant::new(role, airline_id))
+    }
+    // ====================================================================
+    /// Removes every customer flight, airlines and their flights are kept
+    pub fn reset(&mut self) {
+        let predecessor = env::predecessor_account_id();
+        assert_eq!(
+            self.owner,
+            predecessor,
+            "Only the contract owner can reset"
+        );
 
-        match self.user_flights.get(&key) {
-            Some(flight) => {        
-                let flight_class = flight.get_flight_class();
-                env::log(format!("Class: {:?}",&flight_class).as_bytes());
-                format!("{:?}",&flight_class)
-            },
-            None => {
-                panic!("Cannot find your flight");
+        // Baggages live under their own prefix, so they must be cleared
+        // before their flight is dropped. The deposits still held go back in full
+        for (key, mut flight) in self.user_flights.to_vec() {
+            if Self::holds_escrow(&flight) {
+                Self::refund_escrow(flight.get_escrow().as_ref().unwrap());
             }
+            let initial_storage = env::storage_usage();
+            flight.clear_baggages();
+            self.user_flights.remove(&key);
+            self.update_storage(&key.0, initial_storage);
+        }
+        for (group_id, group) in self.groups.to_vec() {
+            let initial_storage = env::storage_usage();
+            self.groups.remove(&group_id);
+            self.update_storage(group.get_leader(), initial_storage);
         }
+    
+        env::log("Reset successfully".as_bytes());
     }
 
-    #[payable]
-    pub fn accept(&mut self, flight_id: FlightId) {
-        self.assert_initialized();
+    /// First step of the ownership transfer, the new owner must accept it
+    pub fn propose_owner(&mut self, new_owner: AccountId) {
+        let predecessor = env::predecessor_account_id();
+        assert_eq!(
+            self.owner,
+            predecessor,
+            "Only the contract owner can propose a new owner"
+        );
+        assert!(
+            env::is_valid_account_id(new_owner.as_bytes()),
+            "Owner account is invalid"
+        );
 
-        let customer_id = env::predecessor_account_id();
-        let key = &(customer_id, flight_id);
+        Event::OwnerProposed {
+            owner: &self.owner,
+            pending_owner: &new_owner
+        }.emit();
+        self.pending_owner = Some(new_owner);
+    }
 
-        match self.user_flights.get(&key) {
-            Some(mut flight) => {    
-                self.assert_state(
-                    &flight, 
-                    FlightState::Idle,
-                );
-                let fee = flight.get_fee();
-                let deposit = env::attached_deposit();
+    pub fn accept_owner(&mut self) {
+        let predecessor = env::predecessor_account_id();
+        match self.pending_owner.take() {
+            Some(pending_owner) => {
                 assert_eq!(
-                    to_yoto(fee),
-                    deposit,
-                    "You must pay {} NEAR", fee
+                    pending_owner,
+                    predecessor,
+                    "Only the proposed owner can accept the ownership"
                 );
 
-                flight.set_state(FlightState::Checked);
-                self.user_flights.insert(&key,&flight);
-                env::log("Your baggages are checked".as_bytes());
+                Event::OwnerChanged {
+                    old_owner: &self.owner,
+                    new_owner: &pending_owner
+                }.emit();
+                self.owner = pending_owner;
             },
             None => {
-                panic!("Cannot find your flight");
+                panic!("There is no proposed owner");
             }
         }
-
     }
 
-    pub fn remove_baggage(&mut self, flight_id: FlightId, baggage_id: BaggageId) {
-        self.assert_initialized();
+    pub fn add_airline(
+        &mut self,
+        iata_code: String,
+        payment_account: AccountId
+    ) -> AirlineId {
+        let predecessor = env::predecessor_account_id();
+        assert_eq!(
+            self.owner,
+            predecessor,
+            "Only the contract owner can add an airline"
+        );
+        assert!(
+            iata_code.len() == 2 && iata_code.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()),
+            "IATA code must be 2 uppercase letters or digits"
+        );
+        assert!(
+            self.airlines.values().all(|airline| *airline.get_iata_code() != iata_code),
+            "IATA code {} is already used", iata_code
+        );
+        Self::assert_payment_account(&payment_account);
 
-        let customer_id = env::predecessor_account_id();
-        let key = &(customer_id, flight_id);
+        let airline_id = self.count_airline;
+        let airline = Airline::new(
+            airline_id,
+            iata_code,
+            payment_account
+        );
+        self.airlines.insert(&airline_id, &airline);
+        self.count_airline += 1;
 
-        match self.user_flights.get(&key) {
-            Some(mut flight) => {        
-                self.assert_state(&flight, FlightState::Idle);
-                flight.remove_baggage(baggage_id);
-                self.user_flights.insert(&key,&flight);
-                env::log("Remove baggage successfully".as_bytes());
-            },
-            None => {
-                panic!("Cannot find your flight");
-            }
+        Event::AirlineAdded {
+            airline_id,
+            iata_code: airline.get_iata_code(),
+            payment_account: airline.get_payment_account()
+        }.emit();
+
+        airline_id
+    }
+
+    /// The owner grants every role,
+    /// airline admins grant the staff roles of their airline
+    pub fn grant_role(
+        &mut self,
+        account_id: AccountId,
+        role: Role,
+        airline_id: Option<AirlineId>
+    ) {
+        assert!(
+            env::is_valid_account_id(account_id.as_bytes()),
+            "Account is invalid"
+        );
+        self.assert_can_manage_role(role, airline_id);
+
+        let grant = RoleGrant::new(role, airline_id);
+        let mut grants = self.roles.get(&account_id).unwrap_or_default();
+        if !grants.contains(&grant) {
+            grants.push(grant);
+            self.roles.insert(&account_id, &grants);
         }
+
+        Event::RoleGranted {
+            account_id: &account_id,
+            role,
+            airline_id
+        }.emit();
     }
 
-    pub fn remove_all_baggages(&mut self, flight_id: FlightId) {
-        self.assert_initialized();
+    pub fn revoke_role(
+        &mut self,
+        account_id: AccountId,
+        role: Role,
+        airline_id: Option<AirlineId>
+    ) {
+        self.assert_can_manage_role(role, airline_id);
 
-        let customer_id = env::predecessor_account_id();
-        let key = &(customer_id, flight_id);
+        let grant = RoleGrant::new(role, airline_id);
+        let mut grants = self.roles.get(&account_id).unwrap_or_default();
+        grants.retain(|other| *other != grant);
+        if grants.is_empty() {
+            self.roles.remove(&account_id);
+        } else {
+            self.roles.insert(&account_id, &grants);
+        }
 
-        match self.user_flights.get(&key) {
+        Event::RoleRevoked {
+            account_id: &account_id,
+            role,
+            airline_id
+        }.emit();
+    }
+
+    /// Future payouts go to the new account,
+    /// deposits already in the contract are kept as is
+    pub fn set_payment_account(&mut self, airline_id: AirlineId, payment_account: AccountId) {
+        let predecessor = env::predecessor_account_id();
+        assert_eq!(
+            self.owner,
+            predecessor,
+            "Only the contract owner can set the payment account"
+        );
+        Self::assert_payment_account(&payment_account);
+
+        let mut airline = self.get_airline(airline_id);
+        Event::PaymentAccountChanged {
+            airline_id,
+            old_payment_account: airline.get_payment_account(),
+            new_payment_account: &payment_account
+        }.emit();
+        airline.set_payment_account(payment_account);
+        self.airlines.insert(&airline_id, &airline);
+    }
+
+    /// Whitelists a USD stablecoin for paying the fees,
+    /// the fee is charged in whole units of the token
+    pub fn add_token(&mut self, token_id: AccountId, decimals: u8) {
+        let predecessor = env::predecessor_account_id();
+        assert_eq!(
+            self.owner,
+            predecessor,
+            "Only the contract owner can add a token"
+        );
+        assert!(
+            env::is_valid_account_id(token_id.as_bytes()),
+            "Token account is invalid"
+        );
+        assert!(
+            decimals <= 24,
+            "Token decimals cannot be more than 24"
+        );
+
+        self.accepted_tokens.insert(&token_id, &decimals);
+        env::log(format!("Token {} accepted", token_id).as_bytes());
+    }
+
+    /// Stops new payments with the token, deposits already made are kept
+    pub fn remove_token(&mut self, token_id: AccountId) {
+        let predecessor = env::predecessor_account_id();
+        assert_eq!(
+            self.owner,
+            predecessor,
+            "Only the contract owner can remove a token"
+        );
+
+        self.accepted_tokens.remove(&token_id);
+        env::log(format!("Token {} removed", token_id).as_bytes());
+    }
+
+    /// Sets the oracle giving the NEAR price in USD, prices older than
+    /// `max_price_age` seconds are refused
+    pub fn set_price_oracle(
+        &mut self,
+        oracle_id: AccountId,
+        asset_id: String,
+        max_price_age: u64
+    ) {
+        let predecessor = env::predecessor_account_id();
+        assert_eq!(
+            self.owner,
+            predecessor,
+            "Only the contract owner can set the price oracle"
+        );
+        assert!(
+            env::is_valid_account_id(oracle_id.as_bytes()),
+            "Oracle account is invalid"
+        );
+
+        env::log(format!("Price oracle set to {}", oracle_id).as_bytes());
+        self.price_oracle = Some(PriceOracle::new(
+            oracle_id,
+            asset_id,
+            max_price_age
+        ));
+    }
+
+    /// The part of each payout kept by the platform, in basis points.
+    /// It applies to the payouts made after the change
+    pub fn set_commission(&mut self, commission: BasisPoints) {
+        let predecessor = env::predecessor_account_id();
+        assert_eq!(
+            self.owner,
+            predecessor,
+            "Only the contract owner can set the commission"
+        );
+        assert!(
+            commission <= MAX_BASIS_POINTS,
+            "Commission cannot be more than {} basis points", MAX_BASIS_POINTS
+        );
+
+        Event::CommissionChanged {
+            old_commission: self.commission,
+            new_commission: commission
+        }.emit();
+        self.commission = commission;
+    }
+
+    pub fn set_treasury(&mut self, treasury: AccountId) {
+        let predecessor = env::predecessor_account_id();
+        assert_eq!(
+            self.owner,
+            predecessor,
+            "Only the contract owner can set the treasury"
+        );
+        Self::assert_payment_account(&treasury);
+
+        Event::TreasuryChanged {
+            old_treasury: &self.treasury,
+            new_treasury: &treasury
+        }.emit();
+        self.treasury = treasury;
+    }
+
+    /// Sends the commissions of a token (`None` is NEAR) to the treasury,
+    /// all of them when `amount` is not given
+    pub fn withdraw_commission(&mut self, token_id: Option<AccountId>, amount: Option<U128>) -> Promise {
+        self.assert_not_paused();
+        let predecessor = env::predecessor_account_id();
+        assert!(
+            predecessor == self.owner || predecessor == self.treasury,
+            "Only the contract owner or the treasury can withdraw the commission"
+        );
+
+        let available = self.commissions.get(&token_id).unwrap_or(0);
+        let amount = amount.map(|amount| amount.0).unwrap_or(available);
+        assert!(
+            amount > 0,
+            "There is no commission to withdraw"
+        );
+        assert!(
+            amount <= available,
+            "Not enough commission, only {} is available", available
+        );
+
+        if amount == available {
+            self.commissions.remove(&token_id);
+        } else {
+            self.commissions.insert(&token_id, &(available - amount));
+        }
+        Event::CommissionWithdrawn {
+            treasury: &self.treasury,
+            token_id: token_id.as_ref(),
+            amount: U128(amount)
+        }.emit();
+        Self::pay(self.treasury.clone(), &token_id, amount).then(
+            ext_self::on_withdraw_commission(
+                token_id,
+                U128(amount),
+                &env::current_account_id(),
+                0,
+                GAS_FOR_PAYOUT_CALLBACK
+            )
+        )
+    }
+
+    /// Puts the commission back when the transfer failed
+    #[private]
+    pub fn on_withdraw_commission(&mut self, token_id: Option<AccountId>, amount: U128) -> bool {
+        if Self::is_promise_success() {
+            return true;
+        }
+
+        let total = self.commissions.get(&token_id).unwrap_or(0);
+        self.commissions.insert(&token_id, &(total + amount.0));
+        env::log(format!("Commission withdrawal failed, {} is kept", amount.0).as_bytes());
+        false
+    }
+
+    /// Opens a flight of the airline for registry
+    pub fn add_flight(&mut self, airline_id: AirlineId, flight_id: FlightId) {
+        let airline = self.get_airline(airline_id);
+        self.assert_role(
+            Role::AirlineAdmin,
+            Some(airline_id),
+            "Only the airline admins can add a flight"
+        );
+        assert!(
+            self.flights.get(&flight_id).is_none(),
+            "This flight was added"
+        );
+
+        self.flights.insert(&flight_id, &Flight::new(flight_id, airline_id));
+        env::log(format!(
+            "Flight {} added for {}",
+            flight_id,
+            airline.get_iata_code()
+        ).as_bytes());
+    }
+
+    /// Sets the allowance of every flight of the class,
+    /// `None` goes back to the default of 3 baggages
+    pub fn set_class_allowance(&mut self, flight_class: FlightClass, allowance: Option<Allowance>) {
+        let predecessor = env::predecessor_account_id();
+        assert_eq!(
+            self.owner,
+            predecessor,
+            "Only the contract owner can set the class allowance"
+        );
+
+        match allowance {
+            Some(allowance) => {
+                allowance.assert_valid();
+                self.class_allowances.insert(&flight_class, &allowance);
+            },
+            None => {
+                self.class_allowances.remove(&flight_class);
+            }
+        }
+        env::log(format!("Allowance of {:?} class updated", flight_class).as_bytes());
+    }
+
+    /// Sets the thresholds above which the baggages of the class pay a surcharge,
+    /// `None` removes the surcharges
+    pub fn set_surcharge_policy(&mut self, flight_class: FlightClass, policy: Option<SurchargePolicy>) {
+        let predecessor = env::predecessor_account_id();
+        assert_eq!(
+            self.owner,
+            predecessor,
+            "Only the contract owner can set the surcharge policy"
+        );
+
+        match policy {
+            Some(policy) => {
+                policy.assert_valid();
+                self.surcharge_policies.insert(&flight_class, &policy);
+            },
+            None => {
+                self.surcharge_policies.remove(&flight_class);
+            }
+        }
+        env::log(format!("Surcharge policy of {:?} class updated", flight_class).as_bytes());
+    }
+
+    /// Sets the pricing and limits of a baggage kind,
+    /// `None` goes back to the default rule of the kind
+    pub fn set_kind_rule(&mut self, kind: BaggageKind, rule: Option<KindRule>) {
+        let predecessor = env::predecessor_account_id();
+        assert_eq!(
+            self.owner,
+            predecessor,
+            "Only the contract owner can set the kind rule"
+        );
+
+        match rule {
+            Some(rule) => {
+                rule.assert_valid();
+                self.kind_rules.insert(&kind, &rule);
+            },
+            None => {
+                self.kind_rules.remove(&kind);
+            }
+        }
+        env::log(format!("Rule of {:?} baggages updated", kind).as_bytes());
+    }
+
+    /// Adds or moves an airport, `None` removes it.
+    /// The flights registered before keep their distance
+    pub fn set_airport(&mut self, code: String, airport: Option<Airport>) {
+        let predecessor = env::predecessor_account_id();
+        assert_eq!(
+            self.owner,
+            predecessor,
+            "Only the contract owner can set an airport"
+        );
+        assert_iata_code(&code);
+
+        match airport {
+            Some(airport) => {
+                airport.assert_valid();
+                self.airports.insert(&code, &airport);
+            },
+            None => {
+                self.airports.remove(&code);
+            }
+        }
+        env::log(format!("Airport {} updated", code).as_bytes());
+    }
+
+    /// The distance of the flight is then derived from its airports
+    /// instead of the one given by the customer
+    pub fn set_flight_route(&mut self, flight_id: FlightId, origin: String, destination: String) {
+        let mut flight = self.get_flight(flight_id);
+        self.assert_role(
+            Role::AirlineAdmin,
+            Some(*flight.get_airline_id()),
+            "Only the airline admins can set the flight route"
+        );
+        assert_ne!(
+            origin,
+            destination,
+            "The origin and destination must be different airports"
+        );
+        let distance = self.get_distance(origin.clone(), destination.clone());
+
+        flight.set_route(origin, destination);
+        self.flights.insert(&flight_id, &flight);
+        env::log(format!(
+            "Route of flight {} updated, {} miles",
+            flight_id,
+            distance
+        ).as_bytes());
+    }
+
+    /// Sets the frequent-flyer tier of an account with the airline, `None` removes it
+    pub fn set_loyalty_tier(&mut self, airline_id: AirlineId, account_id: AccountId, tier: Option<LoyaltyTier>) {
+        self.get_airline(airline_id);
+        self.assert_role(
+            Role::AirlineAdmin,
+            Some(airline_id),
+            "Only the airline admins can set a loyalty tier"
+        );
+
+        let key = &(airline_id, account_id.clone());
+        match tier {
+            Some(tier) => {
+                self.loyalty_tiers.insert(key, &tier);
+            },
+            None => {
+                self.loyalty_tiers.remove(key);
+            }
+        }
+        env::log(format!("Tier of {} updated to {:?}", account_id, tier).as_bytes());
+    }
+
+    /// Overrides the benefit of a tier for the airline,
+    /// `None` goes back to the default benefit of the tier
+    pub fn set_tier_benefit(&mut self, airline_id: AirlineId, tier: LoyaltyTier, benefit: Option<TierBenefit>) {
+        self.get_airline(airline_id);
+        self.assert_role(
+            Role::AirlineAdmin,
+            Some(airline_id),
+            "Only the airline admins can set a tier benefit"
+        );
+
+        match benefit {
+            Some(benefit) => {
+                benefit.assert_valid();
+                self.tier_benefits.insert(&(airline_id, tier), &benefit);
+            },
+            None => {
+                self.tier_benefits.remove(&(airline_id, tier));
+            }
+        }
+        env::log(format!("Benefit of {:?} tier updated", tier).as_bytes());
+    }
+
+    /// Overrides the allowance of the class for one flight,
+    /// `None` goes back to the allowance of the class
+    pub fn set_flight_allowance(
+        &mut self,
+        flight_id: FlightId,
+        flight_class: FlightClass,
+        allowance: Option<Allowance>
+    ) {
+        let flight = self.get_flight(flight_id);
+        self.assert_role(
+            Role::AirlineAdmin,
+            Some(*flight.get_airline_id()),
+            "Only the airline admins can set the flight allowance"
+        );
+
+        match allowance {
+            Some(allowance) => {
+                allowance.assert_valid();
+                self.flight_allowances.insert(&(flight_id, flight_class), &allowance);
+            },
+            None => {
+                self.flight_allowances.remove(&(flight_id, flight_class));
+            }
+        }
+        env::log(format!(
+            "Allowance of {:?} class updated for flight {}",
+            flight_class,
+            flight_id
+        ).as_bytes());
+    }
+
+    /// The guardian can only pause, unpausing is left to the owner
+    pub fn set_guardian(&mut self, guardian: Option<AccountId>) {
+        let predecessor = env::predecessor_account_id();
+        assert_eq!(
+            self.owner,
+            predecessor,
+            "Only the contract owner can set the guardian"
+        );
+        if let Some(guardian) = &guardian {
+            assert!(
+                env::is_valid_account_id(guardian.as_bytes()),
+                "Guardian account is invalid"
+            );
+        }
+
+        Event::GuardianChanged {
+            guardian: guardian.as_ref()
+        }.emit();
+        self.guardian = guardian;
+    }
+
+    /// Stops new registries, baggages, deposits and payouts,
+    /// views and refunds keep working
+    pub fn pause(&mut self) {
+        let predecessor = env::predecessor_account_id();
+        assert!(
+            predecessor == self.owner || Some(&predecessor) == self.guardian.as_ref(),
+            "Only the contract owner or the guardian can pause"
+        );
+        assert!(
+            !self.paused,
+            "The contract is already paused"
+        );
+
+        self.paused = true;
+        Event::Paused {
+            account_id: &predecessor
+        }.emit();
+    }
+
+    pub fn unpause(&mut self) {
+        let predecessor = env::predecessor_account_id();
+        assert_eq!(
+            self.owner,
+            predecessor,
+            "Only the contract owner can unpause"
+        );
+        assert!(
+            self.paused,
+            "The contract is not paused"
+        );
+
+        self.paused = false;
+        Event::Unpaused {
+            account_id: &predecessor
+        }.emit();
+    }
+
+    // ====================================================================
+    /// Deploys the wasm passed as raw call input to this account,
+    /// then calls `migrate` on the new code
+    pub fn upgrade(&self) -> Promise {
+        let predecessor = env::predecessor_account_id();
+        assert_eq!(
+            self.owner,
+            predecessor,
+            "Only the contract owner can upgrade"
+        );
+
+        let code = env::input().expect("Missing contract code");
+        assert!(!code.is_empty(), "Missing contract code");
+
+        env::log(format!("Upgrading contract, code size: {}", code.len()).as_bytes());
+
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call(
+                b"migrate".to_vec(),
+                vec![],
+                0,
+                GAS_FOR_MIGRATE
+            )
+    }
+
+    /// Reads back the state written by the previous code.
+    /// There is no conversion between layouts: a deployment whose `Contract`,
+    /// `FlightDetail` or `Baggage` have other fields must be deployed again.
+    /// The deploy and `migrate` are one batch, so a failed migration keeps the old code
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let state = env::storage_read(b"STATE").expect("Contract state not found");
+        let contract = Self::try_from_slice(&state)
+            .unwrap_or_else(|_| panic!("The contract state has another layout, deploy the contract again"));
+        env::log("Migrate successfully".as_bytes());
+        contract
+    }
+
+    // ====================================================================
+    pub fn registry(
+        &mut self, 
+        flight_id: FlightId,
+        flight_class: FlightClass,
+        distance: Distance
+    ) {
+        self.assert_not_paused();
+        assert!(
+            distance >= 0.0,
+            "The distance cannot be negative"
+        );
+        let distance = self.get_flight_distance(flight_id, distance);
+
+        let customer = env::predecessor_account_id();
+        let key = (customer, flight_id);
+        match self.user_flights.get(&key) {
+            Some(_flight) => {
+                panic!("This flight was registered by you");
+            },
+            None => {
+                let initial_storage = env::storage_usage();
+                let new_flight = FlightDetail::new(
+                    &key.0,
+                    flight_id,
+                    flight_class,
+                    distance
+                );
+                self.user_flights.insert(&key, &new_flight);
+                self.update_storage(&key.0, initial_storage);
+                env::log("Registry successfully".as_bytes());
+            }
+        }
+    }
+
+    /// Registers a flight with connections, possibly on other airlines.
+    /// The baggages are checked once, for the first flight
+    pub fn registry_itinerary(
+        &mut self,
+        flight_class: FlightClass,
+        segments: Vec<Segment>
+    ) {
+        self.assert_not_paused();
+        assert!(
+            segments.len() >= 2 && segments.len() <= MAX_SEGMENTS,
+            "An itinerary has from 2 to {} segments", MAX_SEGMENTS
+        );
+        // The payout is split by distance, which the customer cannot choose
+        let airline_id = *self.get_flight(segments[0].flight_id).get_airline_id();
+        let interline = segments
+            .iter()
+            .any(|segment| *self.get_flight(segment.flight_id).get_airline_id() != airline_id);
+        for (index, segment) in segments.iter().enumerate() {
+            assert!(
+                segment.distance >= 0.0,
+                "The distance cannot be negative"
+            );
+            assert!(
+                segments[..index].iter().all(|other| other.flight_id != segment.flight_id),
+                "The flight {} is twice in the itinerary", segment.flight_id
+            );
+            assert!(
+                !interline || self.get_flight(segment.flight_id).get_route().is_some(),
+                "The flight {} has no route, it is needed to split the payout between airlines", segment.flight_id
+            );
+        }
+
+        let customer = env::predecessor_account_id();
+        let key = (customer, segments[0].flight_id);
+        match self.user_flights.get(&key) {
+            Some(_flight) => {
+                panic!("This flight was registered by you");
+            },
+            None => {
+                let initial_storage = env::storage_usage();
+                let new_flight = FlightDetail::new_itinerary(
+                    &key.0,
+                    flight_class,
+                    segments
+                        .iter()
+                        .map(|segment| Segment::new(
+                            segment.flight_id,
+                            self.get_flight_distance(segment.flight_id, segment.distance)
+                        ))
+                        .collect()
+                );
+                self.user_flights.insert(&key, &new_flight);
+                self.update_storage(&key.0, initial_storage);
+                env::log("Registry successfully".as_bytes());
+            }
+        }
+    }
+
+    /// Starts a group on a registered flight, the caller leads it
+    /// and pays for the baggages of every member
+    pub fn create_group(&mut self, flight_id: FlightId) -> GroupId {
+        self.assert_not_paused();
+
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(mut flight) => {
+                self.assert_state(&flight, FlightState::Idle);
+                Self::assert_poolable(&flight);
+
+                let initial_storage = env::storage_usage();
+                let group_id = self.count_group;
+                self.groups.insert(&group_id, &Group::new(group_id, flight_id, key.0.clone()));
+                flight.set_group_id(Some(group_id));
+                self.user_flights.insert(key,&flight);
+                self.update_storage(&key.0, initial_storage);
+                self.count_group += 1;
+
+                env::log(format!("Group {} created for flight {}", group_id, flight_id).as_bytes());
+                group_id
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+    }
+
+    /// The invited customer joins with `join_group`
+    pub fn invite_to_group(&mut self, group_id: GroupId, account_id: AccountId) {
+        let mut group = self.groups.get(&group_id).expect("Cannot find the group");
+        let leader_key = &(env::predecessor_account_id(), *group.get_flight_id());
+        assert!(
+            group.is_leader(&leader_key.0),
+            "Only the leader of the group can invite"
+        );
+        self.assert_state(&self.user_flights.get(leader_key).unwrap(), FlightState::Idle);
+
+        let initial_storage = env::storage_usage();
+        group.invite(account_id.clone());
+        self.groups.insert(&group_id, &group);
+        self.update_storage(&leader_key.0, initial_storage);
+        env::log(format!("{} invited to group {}", account_id, group_id).as_bytes());
+    }
+
+    /// Pools the allowance of the flight registered by the caller
+    /// with the group, in the same class
+    pub fn join_group(&mut self, group_id: GroupId) {
+        self.assert_not_paused();
+
+        let mut group = self.groups.get(&group_id).expect("Cannot find the group");
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, *group.get_flight_id());
+        let mut flight = self.user_flights.get(key).expect("Cannot find your flight");
+        self.assert_state(&flight, FlightState::Idle);
+        Self::assert_poolable(&flight);
+
+        let leader = self.user_flights
+            .get(&(group.get_leader().clone(), key.1))
+            .unwrap();
+        self.assert_state(&leader, FlightState::Idle);
+        assert_eq!(
+            flight.get_flight_class(),
+            leader.get_flight_class(),
+            "Your class must be {:?} to join this group", leader.get_flight_class()
+        );
+
+        let initial_storage = env::storage_usage();
+        group.join(key.0.clone());
+        self.groups.insert(&group_id, &group);
+        flight.set_group_id(Some(group_id));
+        self.user_flights.insert(key,&flight);
+        self.update_storage(group.get_leader(), initial_storage);
+        env::log(format!("{} joined group {}", key.0, group_id).as_bytes());
+    }
+
+    /// Before the payment, the remaining baggages must fit in the allowance
+    /// of the remaining members. The leader leaves last, which removes the group
+    pub fn leave_group(&mut self, flight_id: FlightId) {
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+        let mut flight = self.user_flights.get(key).expect("Cannot find your flight");
+        self.assert_state(&flight, FlightState::Idle);
+        let group_id = flight.get_group_id().expect("Your flight is not in a group");
+        let mut group = self.groups.get(&group_id).unwrap();
+
+        let initial_storage = env::storage_usage();
+        flight.set_group_id(None);
+        self.user_flights.insert(key,&flight);
+        if group.is_leader(&key.0) && group.get_members().len() == 1 {
+            self.groups.remove(&group_id);
+        } else {
+            group.leave(&key.0);
+            self.groups.insert(&group_id, &group);
+
+            let baggages = self.get_group_baggages(&group);
+            self.get_allowance(flight_id, *flight.get_flight_class())
+                .pooled(group.get_members().len())
+                .assert_within(
+                    baggages.len() as u64,
+                    baggages.iter().map(|baggage| *baggage.get_billed_weight()).sum()
+                );
+        }
+        self.get_allowance(flight_id, *flight.get_flight_class()).assert_within(
+            flight.get_baggages().len(),
+            flight.get_total_weight()
+        );
+        self.update_storage(group.get_leader(), initial_storage);
+        env::log(format!("{} left group {}", key.0, group_id).as_bytes());
+    }
+
+    pub fn add_baggage(
+        &mut self, 
+        flight_id: FlightId, 
+        baggage_weight: Weight,
+        dimensions: Option<Dimensions>,
+        kind: Option<BaggageKind>,
+        dangerous_goods: Option<Vec<DeclaredItem>>
+    ) -> BaggageId {
+        self.assert_not_paused();
+
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(mut flight) => {
+                self.assert_state(&flight, FlightState::Idle);
+                self.insert_baggage(
+                    key,
+                    &mut flight,
+                    baggage_weight,
+                    dimensions,
+                    kind,
+                    dangerous_goods
+                )
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+    }
+
+    /// Adds a baggage to a paid flight, before its baggages are delivered.
+    /// The flight awaits the top up of the incremental fee
+    pub fn add_extra_baggage(
+        &mut self,
+        flight_id: FlightId,
+        baggage_weight: Weight,
+        dimensions: Option<Dimensions>,
+        kind: Option<BaggageKind>,
+        dangerous_goods: Option<Vec<DeclaredItem>>
+    ) -> BaggageId {
+        self.assert_not_paused();
+
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(mut flight) => {
+                if !matches!(
+                    flight.get_state(),
+                    FlightState::Checked | FlightState::AwaitingTopUp
+                ) {
+                    panic!(
+                        "You can only add an extra baggage in Checked or AwaitingTopUp state, not {:?}",
+                        flight.get_state()
+                    );
+                }
+                assert!(
+                    !flight.has_departed(),
+                    "You cannot add an extra baggage after the first flight departed"
+                );
+
+                let baggage_id = self.insert_baggage(
+                    key,
+                    &mut flight,
+                    baggage_weight,
+                    dimensions,
+                    kind,
+                    dangerous_goods
+                );
+                self.reconcile_group(key, &mut flight);
+                self.user_flights.insert(key,&flight);
+                baggage_id
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+    }
+
+    pub fn check_number_baggages(
+        &mut self, 
+        flight_id: FlightId
+    ) -> u64 {
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+        match self.user_flights.get(key) {
+            Some(flight) => {
+                let baggages = flight.get_baggages();
+                env::log(format!("Number of baggages: {}",baggages.len()).as_bytes());
+                baggages.len()
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+    }
+
+    pub fn check_baggage(
+        &mut self, 
+        flight_id: FlightId,
+        baggage_id: BaggageId
+    ) -> Baggage {
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(flight) => {
+                let baggages = flight.get_baggages();
+                
+                match baggages.get(&baggage_id) {
+                    Some(baggage) => {
+                        env::log(format!(
+                            "[{}] Weight={}", 
+                            baggage.get_id(), 
+                            baggage.get_weight()
+                        ).as_bytes());
+                        baggage
+                    },
+                    None => {
+                        panic!("Cannot find your baggage");
+                    }
+                }
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+    }
+
+    /// The oversize and overweight baggages, with their surcharge in USD
+    pub fn check_surcharges(&mut self, flight_id: FlightId) -> Vec<BaggageSurcharge> {
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(flight) => {
+                let policy = self.get_fee_policy(key, flight.get_flight_class());
+                flight.get_baggages()
+                    .values()
+                    .map(|baggage| BaggageSurcharge::new(
+                        &baggage,
+                        policy.get_surcharge_policy(&baggage)
+                    ))
+                    .collect()
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+    }
+
+    pub fn check_itinerary(&mut self, flight_id: FlightId) -> Vec<Segment> {
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(flight) => {
+                flight.get_segments().clone()
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+    }
+
+    pub fn check_fee(&mut self, flight_id: FlightId) -> Balance {
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(flight) => {        
+                let price = self.get_flight_fee(key, &flight);
+                env::log(format!("Your price: {} USD",&price).as_bytes());
+                price
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+    }
+
+    /// The fee item by item: the class strategy, then the loyalty tier
+    pub fn check_fee_quote(&self, customer_id: AccountId, flight_id: FlightId) -> FeeQuote {
+        let key = &(customer_id, flight_id);
+        let flight = self.user_flights.get(key).expect("Cannot find the flight of the customer");
+        self.get_flight_quote(key, &flight)
+    }
+
+    pub fn check_state(&mut self, flight_id: FlightId) -> String {
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(flight) => {        
+                env::log(format!("State: {:?}",flight.get_state()).as_bytes());
+                format!("{:?}",&flight.get_state())
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+    }
+
+    
+    pub fn check_class(&mut self, flight_id: FlightId) -> String {
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(flight) => {        
+                let flight_class = flight.get_flight_class();
+                env::log(format!("Class: {:?}",&flight_class).as_bytes());
+                format!("{:?}",&flight_class)
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+    }
+
+    pub fn check_escrow(&mut self, flight_id: FlightId) -> Option<Escrow> {
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(flight) => {
+                flight.get_escrow().clone()
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+    }
+
+    /// The fee is in USD, so the NEAR price is read from the oracle first.
+    /// The deposit above the converted fee is given back
+    #[payable]
+    pub fn accept(&mut self, flight_id: FlightId) -> Promise {
+        self.accept_for(env::predecessor_account_id(), flight_id)
+    }
+
+    /// Pays the fee of the flight of another customer who approved the caller
+    /// with `approve_sponsor`, the refunds then go back to the caller
+    #[payable]
+    pub fn accept_for(&mut self, customer_id: AccountId, flight_id: FlightId) -> Promise {
+        self.assert_not_paused();
+
+        let oracle = self.price_oracle.as_ref().expect("The price oracle is not set");
+        let payer = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(flight) => {    
+                self.assert_state(
+                    &flight, 
+                    FlightState::Idle,
+                );
+                self.assert_payer(key, &flight);
+                Self::assert_sponsor(key, &flight, &payer);
+                self.assert_inspected(&flight);
+                let fee = self.get_flight_fee(key, &flight);
+                let deposit = env::attached_deposit();
+                assert!(
+                    deposit > 0,
+                    "You must pay {} USD in NEAR", fee
+                );
+
+                ext_oracle::get_price_data(
+                    Some(vec![oracle.get_asset_id().clone()]),
+                    oracle.get_oracle_id(),
+                    0,
+                    GAS_FOR_PRICE_DATA
+                ).then(ext_self::on_accept_price(
+                    key.0.clone(),
+                    payer,
+                    flight_id,
+                    U128(fee),
+                    U128(deposit),
+                    &env::current_account_id(),
+                    0,
+                    GAS_FOR_ACCEPT_CALLBACK
+                ))
+            },
+            None => {
+                if key.0 == payer {
+                    panic!("Cannot find your flight");
+                }
+                panic!("Cannot find the flight of {}", key.0);
+            }
+        }
+
+    }
+
+    /// Locks the fee converted at the oracle price,
+    /// on any failure the whole deposit goes back to the payer
+    #[private]
+    pub fn on_accept_price(
+        &mut self,
+        customer_id: AccountId,
+        payer: AccountId,
+        flight_id: FlightId,
+        fee: U128,
+        deposit: U128
+    ) -> bool {
+        let key = &(customer_id, flight_id);
+        let (required, rate) = match self.get_accept_price(key, fee.0) {
+            Ok(price) => price,
+            Err(message) => {
+                env::log(message.as_bytes());
+                Promise::new(payer).transfer(deposit.0);
+                return false;
+            }
+        };
+        if deposit.0 < required {
+            env::log(format!(
+                "You must pay {} USD, which is {} yoctoNEAR",
+                fee.0,
+                required
+            ).as_bytes());
+            Promise::new(payer).transfer(deposit.0);
+            return false;
+        }
+
+        // The deposit cannot be kept when the customer cannot pay for its record
+        let escrow = Escrow::new(
+            payer.clone(),
+            None,
+            required,
+            fee.0,
+            rate
+        );
+        let escrow_storage = escrow.try_to_vec().unwrap().len() as StorageUsage;
+        if !self.storage_accounts
+            .get(&key.0)
+            .is_some_and(|account| account.can_add_usage(escrow_storage))
+        {
+            env::log("Not enough storage deposit to record your deposit, see storage_deposit".as_bytes());
+            Promise::new(payer).transfer(deposit.0);
+            return false;
+        }
+
+        let mut flight = self.user_flights.get(key).unwrap();
+        let initial_storage = env::storage_usage();
+        flight.set_escrow(escrow);
+        flight.set_state(FlightState::Checked);
+        self.user_flights.insert(key,&flight);
+        self.update_group_state(key, &flight);
+        self.update_storage(&key.0, initial_storage);
+        if deposit.0 > required {
+            Promise::new(payer).transfer(deposit.0 - required);
+        }
+        env::log(format!(
+            "Your baggages are checked, locked {} yoctoNEAR",
+            required
+        ).as_bytes());
+        true
+    }
+
+    /// Pays the fee added by the weights measured at the counter,
+    /// at the rate of the deposit. The excess is given back
+    #[payable]
+    pub fn top_up(&mut self, flight_id: FlightId) {
+        self.top_up_for(env::predecessor_account_id(), flight_id)
+    }
+
+    /// Tops up the deposit paid for another customer,
+    /// only the payer of the deposit can add to it
+    #[payable]
+    pub fn top_up_for(&mut self, customer_id: AccountId, flight_id: FlightId) {
+        self.assert_not_paused();
+
+        let payer = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(mut flight) => {
+                self.assert_state(&flight, FlightState::AwaitingTopUp);
+                self.assert_payer(key, &flight);
+                self.assert_inspected(&flight);
+                let fee = self.get_flight_fee(key, &flight);
+                let mut escrow = flight.get_escrow().clone().expect("Cannot find the deposit");
+                Self::assert_escrow_payer(&escrow, &payer);
+                assert!(
+                    escrow.get_token_id().is_none(),
+                    "You must top up with {}", escrow.get_token_id().as_ref().unwrap()
+                );
+
+                let due = escrow.get_top_up(fee);
+                let deposit = env::attached_deposit();
+                assert!(
+                    deposit >= due,
+                    "You must pay {} yoctoNEAR more", due
+                );
+
+                escrow.top_up(due, fee);
+                flight.set_escrow(escrow);
+                flight.set_state(FlightState::Checked);
+                self.user_flights.insert(key,&flight);
+                self.update_group_state(key, &flight);
+                if deposit > due {
+                    Promise::new(payer).transfer(deposit - due);
+                }
+                env::log("Your baggages are checked".as_bytes());
+            },
+            None => {
+                if key.0 == payer {
+                    panic!("Cannot find your flight");
+                }
+                panic!("Cannot find the flight of {}", key.0);
+            }
+        }
+    }
+
+    /// Accepts the fee paid with a whitelisted fungible token,
+    /// `msg` holds the flight, e.g. `{"flight_id":1}`, and the customer
+    /// when paying for another one, e.g. `{"flight_id":1,"customer_id":"alice.testnet"}`.
+    /// A flight awaiting a top up is topped up with the token of its deposit
+    pub fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String
+    ) -> PromiseOrValue<U128> {
+        self.assert_not_paused();
+
+        let token_id = env::predecessor_account_id();
+        let decimals = self.accepted_tokens
+            .get(&token_id)
+            .unwrap_or_else(|| panic!("The token {} is not accepted", token_id));
+        let message: TransferMessage = serde_json::from_str(&msg)
+            .expect("The message must be {\"flight_id\": <flight_id>}");
+
+        let customer_id = message.customer_id.unwrap_or_else(|| sender_id.clone());
+        let key = &(customer_id, message.flight_id);
+
+        match self.user_flights.get(key) {
+            Some(mut flight) if *flight.get_state() == FlightState::AwaitingTopUp => {
+                self.assert_payer(key, &flight);
+                self.assert_inspected(&flight);
+                let fee = self.get_flight_fee(key, &flight);
+                let mut escrow = flight.get_escrow().clone().expect("Cannot find the deposit");
+                Self::assert_escrow_payer(&escrow, &sender_id);
+                assert_eq!(
+                    escrow.get_token_id().as_ref(),
+                    Some(&token_id),
+                    "You must top up with the token of your deposit"
+                );
+                let due = escrow.get_top_up(fee);
+                assert_eq!(
+                    due,
+                    amount.0,
+                    "You must pay {} {} more", due, token_id
+                );
+
+                escrow.top_up(due, fee);
+                flight.set_escrow(escrow);
+                flight.set_state(FlightState::Checked);
+                self.user_flights.insert(key,&flight);
+                self.update_group_state(key, &flight);
+                env::log("Your baggages are checked".as_bytes());
+            },
+            Some(mut flight) => {
+                self.assert_state(
+                    &flight,
+                    FlightState::Idle,
+                );
+                self.assert_payer(key, &flight);
+                Self::assert_sponsor(key, &flight, &sender_id);
+                self.assert_inspected(&flight);
+                let fee = self.get_flight_fee(key, &flight);
+                let rate = 10u128.pow(decimals as u32);
+                assert_eq!(
+                    fee * rate,
+                    amount.0,
+                    "You must pay {} {}", fee, token_id
+                );
+
+                let initial_storage = env::storage_usage();
+                flight.set_escrow(Escrow::new(
+                    sender_id,
+                    Some(token_id),
+                    amount.0,
+                    fee,
+                    rate
+                ));
+                flight.set_state(FlightState::Checked);
+                self.user_flights.insert(key,&flight);
+                self.update_group_state(key, &flight);
+                self.update_storage(&key.0, initial_storage);
+                env::log("Your baggages are checked".as_bytes());
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+
+        // The whole amount is used
+        PromiseOrValue::Value(U128(0))
+    }
+
+    pub fn remove_baggage(&mut self, flight_id: FlightId, baggage_id: BaggageId) {
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(mut flight) => {        
+                self.assert_state(&flight, FlightState::Idle);
+                let initial_storage = env::storage_usage();
+                flight.remove_baggage(baggage_id);
+                self.user_flights.insert(key,&flight);
+                self.update_storage(&key.0, initial_storage);
+                env::log("Remove baggage successfully".as_bytes());
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+    }
+
+    pub fn remove_all_baggages(&mut self, flight_id: FlightId) {
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
             Some(mut flight) => {        
+                self.assert_state(&flight, FlightState::Idle);
+                let initial_storage = env::storage_usage();
                 flight.clear_baggages();
-                self.user_flights.insert(&key,&flight);
+                self.user_flights.insert(key,&flight);
+                self.update_storage(&key.0, initial_storage);
                 env::log("Remove all baggages successfully".as_bytes());
             },
             None => {
@@ -363,22 +1739,379 @@ impl Contract {
         }
     }
 
-    pub fn deliver_baggage(&mut self, customer_id: AccountId, flight_id: FlightId) {
-        self.assert_initialized();
+    /// Removes a flight without deposit, its storage is given back
+    pub fn remove_flight(&mut self, flight_id: FlightId) {
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(mut flight) => {
+                if !matches!(
+                    flight.get_state(),
+                    FlightState::Idle | FlightState::Claimed | FlightState::Refunded
+                ) {
+                    panic!(
+                        "You cannot remove your flight in {:?} state",
+                        flight.get_state()
+                    );
+                }
+                if let Some(group_id) = *flight.get_group_id() {
+                    assert!(
+                        *flight.get_state() != FlightState::Idle,
+                        "You must leave your group first"
+                    );
+                    // The leader holds the deposit, so it goes last
+                    let initial_storage = env::storage_usage();
+                    let mut group = self.groups.get(&group_id).unwrap();
+                    if group.get_members().len() == 1 {
+                        self.groups.remove(&group_id);
+                    } else {
+                        group.leave(&key.0);
+                        self.groups.insert(&group_id, &group);
+                    }
+                    self.update_storage(group.get_leader(), initial_storage);
+                }
+
+                let initial_storage = env::storage_usage();
+                flight.clear_baggages();
+                self.user_flights.remove(key);
+                self.update_storage(&key.0, initial_storage);
+                env::log("Remove flight successfully".as_bytes());
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+    }
+
+    /// The difference between the measured and declared weights
+    /// which is not billed
+    pub fn set_weight_tolerance(&mut self, airline_id: AirlineId, weight_tolerance: Weight) {
+        let mut airline = self.get_airline(airline_id);
+        self.assert_role(
+            Role::AirlineAdmin,
+            Some(airline_id),
+            "Only the airline admins can set the weight tolerance"
+        );
+        assert!(
+            weight_tolerance >= 0.0,
+            "The weight tolerance cannot be negative"
+        );
+
+        airline.set_weight_tolerance(weight_tolerance);
+        self.airlines.insert(&airline_id, &airline);
+        env::log(format!(
+            "Weight tolerance of {} set to {} lbs",
+            airline.get_iata_code(),
+            weight_tolerance
+        ).as_bytes());
+    }
+
+    /// Records the weight of a baggage on the scale of the counter.
+    /// Out of the tolerance of the airline, the measured weight is billed:
+    /// a paid flight gets the difference back, or awaits a top up
+    pub fn record_weight(
+        &mut self,
+        customer_id: AccountId,
+        flight_id: FlightId,
+        baggage_id: BaggageId,
+        measured_weight: Weight
+    ) {
+        let airline_id = *self.get_flight(flight_id).get_airline_id();
+        self.assert_role(
+            Role::CheckInAgent,
+            Some(airline_id),
+            "Only the check-in agents of the airline can record a weight"
+        );
+        assert!(
+            measured_weight > 0.0,
+            "The baggage weight must be positive"
+        );
+
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(mut flight) => {
+                if !matches!(
+                    flight.get_state(),
+                    FlightState::Idle | FlightState::Checked | FlightState::AwaitingTopUp
+                ) {
+                    panic!(
+                        "You cannot record a weight in {:?} state",
+                        flight.get_state()
+                    );
+                }
+                assert!(
+                    !flight.has_departed(),
+                    "You cannot record a weight after the first flight departed"
+                );
+
+                let old_fee = self.get_flight_fee(key, &flight);
+                let mut baggage = flight.get_baggages()
+                    .get(&baggage_id)
+                    .expect("Cannot find the baggage");
+                baggage.record_weight(
+                    measured_weight,
+                    *self.get_airline(airline_id).get_weight_tolerance()
+                );
+                flight.add_baggage(baggage);
+                let fee = self.get_flight_fee(key, &flight);
+                env::log(format!(
+                    "Baggage {} weighs {} lbs, the fee goes from {} to {} USD",
+                    baggage_id,
+                    measured_weight,
+                    old_fee,
+                    fee
+                ).as_bytes());
+
+                if *flight.get_state() != FlightState::Idle {
+                    self.reconcile_group(key, &mut flight);
+                }
+                self.user_flights.insert(key,&flight);
+            },
+            None => {
+                panic!("Cannot find the flight of the customer");
+            }
+        }
+    }
+
+    /// Clears a baggage flagged by its dangerous goods declaration,
+    /// once it was inspected at the counter
+    pub fn clear_baggage(&mut self, customer_id: AccountId, flight_id: FlightId, baggage_id: BaggageId) {
+        let flight = self.get_flight(flight_id);
+        self.assert_role(
+            Role::CheckInAgent,
+            Some(*flight.get_airline_id()),
+            "Only the check-in agents of the airline can clear a baggage"
+        );
+
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(mut flight) => {
+                if !matches!(
+                    flight.get_state(),
+                    FlightState::Idle | FlightState::Checked | FlightState::AwaitingTopUp
+                ) {
+                    panic!(
+                        "You cannot clear a baggage in {:?} state",
+                        flight.get_state()
+                    );
+                }
+                let mut baggage = flight.get_baggages()
+                    .get(&baggage_id)
+                    .expect("Cannot find the baggage");
+                baggage.clear_inspection();
+                flight.add_baggage(baggage);
+                self.user_flights.insert(key,&flight);
+                env::log(format!("Baggage {} cleared", baggage_id).as_bytes());
+            },
+            None => {
+                panic!("Cannot find the flight of the customer");
+            }
+        }
+    }
+
+    /// Hands the baggages of an itinerary over to its next segment,
+    /// done by the ground handlers of the current segment
+    pub fn transfer_baggage(&mut self, customer_id: AccountId, flight_id: FlightId) {
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(mut flight) => {
+                let segment_flight_id = flight.get_current_segment().flight_id;
+                self.assert_role(
+                    Role::GroundHandler,
+                    Some(*self.get_flight(segment_flight_id).get_airline_id()),
+                    "Only the ground handlers of the airline can transfer the baggages"
+                );
+                self.assert_state(&flight, FlightState::Checked);
+                self.assert_inspected(&flight);
+                assert!(
+                    !flight.is_last_segment(segment_flight_id),
+                    "The baggages are on the last segment, deliver them instead"
+                );
+
+                flight.transfer();
+                self.user_flights.insert(key,&flight);
+                env::log(format!(
+                    "Baggages transferred from flight {} to flight {}",
+                    segment_flight_id,
+                    flight.get_current_segment().flight_id
+                ).as_bytes());
+            },
+            None => {
+                panic!("Cannot find the flight of the customer");
+            }
+        }
+    }
+
+    /// Done by the ground handlers of the last segment,
+    /// once the baggages were transferred through the itinerary.
+    /// `claim_code_hash` is the SHA-256 of the code printed on the baggage receipt,
+    /// which must be random enough not to be guessed from its hash
+    pub fn deliver_baggage(
+        &mut self,
+        customer_id: AccountId,
+        flight_id: FlightId,
+        claim_code_hash: Base58CryptoHash
+    ) {
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(mut flight) => {        
+                let segment_flight_id = flight.get_current_segment().flight_id;
+                self.assert_role(
+                    Role::GroundHandler,
+                    Some(*self.get_flight(segment_flight_id).get_airline_id()),
+                    "Only the ground handlers of the airline can deliver the baggages"
+                );
+                self.assert_state(&flight, FlightState::Checked);
+                self.assert_inspected(&flight);
+                assert!(
+                    flight.is_last_segment(segment_flight_id),
+                    "The baggages must be transferred from flight {} first", segment_flight_id
+                );
+                flight.set_state(FlightState::Delivered);
+                flight.set_claim_code_hash(Some(claim_code_hash.into()));
+                self.user_flights.insert(key,&flight);
+                self.update_group_state(key, &flight);
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+    }
+
+    /// `claim_code` is printed on the baggage receipt,
+    /// so the baggages cannot be claimed before they are picked up
+    #[payable]
+    pub fn claim_baggages(&mut self, flight_id: FlightId, claim_code: String) {
+        self.assert_not_paused();
+
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(mut flight) => {        
+                self.assert_state(&flight, FlightState::Delivered);
+                self.assert_payer(key, &flight);
+                flight.assert_claim_code(&claim_code);
+
+                flight.claim_baggages(None);
+                self.settle_claim(key, &mut flight);
+                env::log("Your baggages are claimed".as_bytes());
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+
+    }
+
+    /// Allows another account to claim the baggages of the flight, until `expires_at`
+    /// (in nanoseconds) when it is set. Authorizing the same delegate again replaces it
+    pub fn authorize_pickup(
+        &mut self,
+        flight_id: FlightId,
+        delegate: AccountId,
+        baggage_ids: Option<Vec<BaggageId>>,
+        expires_at: Option<U64>
+    ) {
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(mut flight) => {
+                if matches!(
+                    flight.get_state(),
+                    FlightState::Claimed | FlightState::Refunded
+                ) {
+                    panic!(
+                        "You cannot authorize a pickup in {:?} state",
+                        flight.get_state()
+                    );
+                }
+                self.assert_group_claimer(key, &flight);
+                assert!(
+                    env::is_valid_account_id(delegate.as_bytes()) && delegate != key.0,
+                    "The delegate account is invalid"
+                );
+                if let Some(baggage_ids) = &baggage_ids {
+                    assert!(!baggage_ids.is_empty(), "The baggages of the pickup cannot be empty");
+                    for baggage_id in baggage_ids {
+                        if flight.get_baggages().get(baggage_id).is_none() {
+                            panic!("Cannot find the baggage {}", baggage_id);
+                        }
+                    }
+                }
+                if let Some(expires_at) = expires_at {
+                    assert!(
+                        expires_at.0 > env::block_timestamp(),
+                        "The pickup must expire in the future"
+                    );
+                }
+
+                let initial_storage = env::storage_usage();
+                flight.authorize(Delegation {
+                    delegate: delegate.clone(),
+                    baggage_ids,
+                    expires_at
+                });
+                self.user_flights.insert(key,&flight);
+                self.update_storage(&key.0, initial_storage);
+                env::log(format!("{} can claim your baggages of flight {}", delegate, flight_id).as_bytes());
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+    }
+
+    /// Allows an account to pay for the flight with `accept_for` or a token transfer,
+    /// `None` withdraws the approval
+    pub fn approve_sponsor(&mut self, flight_id: FlightId, sponsor: Option<AccountId>) {
+        let customer_id = env::predecessor_account_id();
+        let key = &(customer_id, flight_id);
 
-        assert_eq!(
-            env::predecessor_account_id(),
-            self.payment_account,
-            "Only airline can deliver the baggages"
-        );
+        match self.user_flights.get(key) {
+            Some(mut flight) => {
+                self.assert_state(&flight, FlightState::Idle);
+                if let Some(sponsor) = &sponsor {
+                    assert!(
+                        env::is_valid_account_id(sponsor.as_bytes()) && *sponsor != key.0,
+                        "The sponsor account is invalid"
+                    );
+                }
+                let initial_storage = env::storage_usage();
+                flight.set_sponsor(sponsor.clone());
+                self.user_flights.insert(key,&flight);
+                self.update_storage(&key.0, initial_storage);
+                match sponsor {
+                    Some(sponsor) => env::log(format!("{} can pay for your flight {}", sponsor, flight_id).as_bytes()),
+                    None => env::log(format!("Nobody else can pay for your flight {}", flight_id).as_bytes())
+                }
+            },
+            None => {
+                panic!("Cannot find your flight");
+            }
+        }
+    }
 
+    pub fn revoke_pickup(&mut self, flight_id: FlightId, delegate: AccountId) {
+        let customer_id = env::predecessor_account_id();
         let key = &(customer_id, flight_id);
 
-        match self.user_flights.get(&key) {
-            Some(mut flight) => {        
-                self.assert_state(&flight, FlightState::Checked);
-                flight.set_state(FlightState::Delivered);
-                self.user_flights.insert(&key,&flight);
+        match self.user_flights.get(key) {
+            Some(mut flight) => {
+                let initial_storage = env::storage_usage();
+                assert!(
+                    flight.revoke(&delegate),
+                    "{} is not authorized", delegate
+                );
+                self.user_flights.insert(key,&flight);
+                self.update_storage(&key.0, initial_storage);
+                env::log(format!("{} cannot claim your baggages of flight {} anymore", delegate, flight_id).as_bytes());
             },
             None => {
                 panic!("Cannot find your flight");
@@ -386,41 +2119,788 @@ impl Contract {
         }
     }
 
-    #[payable]
-    pub fn claim_baggages(&mut self, flight_id: FlightId) {
-        self.assert_initialized();
+    /// Claims the baggages the caller was authorized for by the customer,
+    /// the flight is claimed once all of its baggages are
+    pub fn claim_for(&mut self, customer_id: AccountId, flight_id: FlightId, claim_code: String) {
+        self.assert_not_paused();
 
-        let customer_id = env::predecessor_account_id();
+        let delegate = env::predecessor_account_id();
         let key = &(customer_id, flight_id);
 
-        match self.user_flights.get(&key) {
-            Some(mut flight) => {        
+        match self.user_flights.get(key) {
+            Some(mut flight) => {
                 self.assert_state(&flight, FlightState::Delivered);
+                self.assert_group_claimer(key, &flight);
+                let delegation = flight
+                    .get_delegation(&delegate)
+                    .cloned()
+                    .unwrap_or_else(|| panic!("You cannot claim the baggages of {}", key.0));
+                assert!(
+                    !delegation.is_expired(),
+                    "Your pickup of the baggages of {} expired", key.0
+                );
+                flight.assert_claim_code(&claim_code);
 
-                flight.set_state(FlightState::Claimed);
-                self.user_flights.insert(&key,&flight);
-                
-                Promise::new(
-                    self.get_payment_account().to_string()
-                ).transfer(
-                    to_yoto(flight.get_fee())
+                let claimed = flight.claim_baggages(Some(&delegation));
+                assert!(
+                    !claimed.is_empty(),
+                    "There is no baggage left for you to claim"
                 );
+                env::log(format!("Baggages {:?} of {} claimed by {}", claimed, key.0, delegate).as_bytes());
+                if flight.is_claimed() {
+                    self.settle_claim(key, &mut flight);
+                } else {
+                    self.user_flights.insert(key,&flight);
+                }
+            },
+            None => {
+                panic!("Cannot find the flight of the customer");
+            }
+        }
+    }
+
+    /// Sends the deposit of a checked flight back to the customer
+    pub fn refund(&mut self, customer_id: AccountId, flight_id: FlightId) {
+        self.assert_role(
+            Role::Arbiter,
+            None,
+            "Only the arbiters can refund"
+        );
+
+        let key = &(customer_id, flight_id);
+
+        match self.user_flights.get(key) {
+            Some(mut flight) => {
+                if !matches!(
+                    flight.get_state(),
+                    FlightState::Checked | FlightState::AwaitingTopUp | FlightState::Delivered
+                ) {
+                    panic!(
+                        "You can only refund in Checked, AwaitingTopUp or Delivered state, not {:?}",
+                        flight.get_state()
+                    );
+                }
+
+                self.assert_payer(key, &flight);
+
+                flight.set_state(FlightState::Refunded);
+                self.user_flights.insert(key,&flight);
+                self.update_group_state(key, &flight);
+
+                Self::refund_escrow(flight.get_escrow().as_ref().expect("Cannot find the deposit"));
+                env::log(format!("Refunded flight {} of {}", flight_id, key.0).as_bytes());
             },
             None => {
                 panic!("Cannot find your flight");
             }
         }
+    }
+
+    /// Pays the earnings of the airline in a token (`None` is NEAR)
+    /// to its payment account, all of them when `amount` is not given
+    pub fn withdraw(
+        &mut self,
+        airline_id: AirlineId,
+        token_id: Option<AccountId>,
+        amount: Option<U128>
+    ) -> Promise {
+        self.assert_not_paused();
+
+        let airline = self.get_airline(airline_id);
+        self.assert_role(
+            Role::AirlineAdmin,
+            Some(airline_id),
+            "Only the airline admins can withdraw"
+        );
+
+        let balance_key = &(airline_id, token_id);
+        let available = self.airline_balances.get(balance_key).unwrap_or(0);
+        let amount = amount.map(|amount| amount.0).unwrap_or(available);
+        assert!(
+            amount > 0,
+            "There is nothing to withdraw"
+        );
+        assert!(
+            amount <= available,
+            "Not enough balance, only {} is available", available
+        );
+
+        if amount == available {
+            self.airline_balances.remove(balance_key);
+        } else {
+            self.airline_balances.insert(balance_key, &(available - amount));
+        }
+        Event::AirlineWithdrawn {
+            airline_id,
+            payment_account: airline.get_payment_account(),
+            token_id: balance_key.1.as_ref(),
+            amount: U128(amount)
+        }.emit();
+        Self::pay(
+            airline.get_payment_account().to_string(),
+            &balance_key.1,
+            amount
+        ).then(ext_self::on_withdraw(
+            airline_id,
+            balance_key.1.clone(),
+            U128(amount),
+            &env::current_account_id(),
+            0,
+            GAS_FOR_PAYOUT_CALLBACK
+        ))
+    }
+
+    /// Credits the airline back when the transfer failed
+    #[private]
+    pub fn on_withdraw(&mut self, airline_id: AirlineId, token_id: Option<AccountId>, amount: U128) -> bool {
+        if Self::is_promise_success() {
+            return true;
+        }
+
+        let balance_key = &(airline_id, token_id);
+        let balance = self.airline_balances.get(balance_key).unwrap_or(0);
+        self.airline_balances.insert(balance_key, &(balance + amount.0));
+        env::log(format!(
+            "Withdrawal of airline {} failed, {} is credited back",
+            airline_id,
+            amount.0
+        ).as_bytes());
+        false
+    }
+
+    /// Records the refund as owed when the transfer failed,
+    /// see `withdraw_owed`
+    #[private]
+    pub fn on_refund(&mut self, account_id: AccountId, token_id: Option<AccountId>, amount: U128) -> bool {
+        if Self::is_promise_success() {
+            return true;
+        }
+
+        let key = &(account_id, token_id);
+        let owed = self.owed.get(key).unwrap_or(0);
+        self.owed.insert(key, &(owed + amount.0));
+        env::log(format!("Refund to {} failed, {} is owed", key.0, amount.0).as_bytes());
+        false
+    }
+
+    /// Sends again the refunds of a token (`None` is NEAR) which failed
+    pub fn withdraw_owed(&mut self, token_id: Option<AccountId>) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let amount = self.owed
+            .remove(&(account_id.clone(), token_id.clone()))
+            .expect("Nothing is owed to you");
+
+        env::log(format!("Withdrawing {} owed to {}", amount, account_id).as_bytes());
+        Self::pay_refund(account_id, token_id, amount)
+    }
+
+    // ====================================================================
+    #[payable]
+    pub fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>
+    ) -> StorageBalance {
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        assert!(
+            env::is_valid_account_id(account_id.as_bytes()),
+            "Account is invalid"
+        );
+        let mut amount = env::attached_deposit();
+        let min_balance = self.storage_balance_bounds().min.0;
+
+        let account = match self.storage_accounts.get(&account_id) {
+            Some(mut account) => {
+                if registration_only.unwrap_or(false) {
+                    // Already registered, the whole deposit goes back
+                    Promise::new(env::predecessor_account_id()).transfer(amount);
+                } else {
+                    account.add_deposit(amount);
+                }
+                account
+            },
+            None => {
+                assert!(
+                    amount >= min_balance,
+                    "The deposit must be at least {} yoctoNEAR", min_balance
+                );
+                if registration_only.unwrap_or(false) {
+                    let refund = amount - min_balance;
+                    if refund > 0 {
+                        Promise::new(env::predecessor_account_id()).transfer(refund);
+                    }
+                    amount = min_balance;
+                }
+                StorageAccount::new(amount, self.account_storage_usage)
+            }
+        };
+        self.storage_accounts.insert(&account_id, &account);
+
+        account.to_balance()
+    }
+
+    #[payable]
+    pub fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+
+        let account_id = env::predecessor_account_id();
+        let mut account = self.storage_accounts
+            .get(&account_id)
+            .unwrap_or_else(|| panic!("The account {} is not registered", account_id));
+        let amount = amount.map(|amount| amount.0).unwrap_or_else(|| account.get_available());
+
+        account.withdraw(amount);
+        self.storage_accounts.insert(&account_id, &account);
+        if amount > 0 {
+            Promise::new(account_id).transfer(amount);
+        }
+
+        account.to_balance()
+    }
+
+    /// Only accounts without flights can unregister, `force` is not supported
+    #[payable]
+    pub fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        assert!(
+            !force.unwrap_or(false),
+            "Force unregister is not supported"
+        );
+
+        let account_id = env::predecessor_account_id();
+        match self.storage_accounts.get(&account_id) {
+            Some(account) => {
+                assert!(
+                    *account.get_usage() <= self.account_storage_usage,
+                    "Remove your flights before unregistering"
+                );
+
+                self.storage_accounts.remove(&account_id);
+                Promise::new(account_id).transfer(*account.get_deposit());
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_accounts
+            .get(&account_id)
+            .map(|account| account.to_balance())
+    }
 
+    pub fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: U128(Balance::from(self.account_storage_usage) * env::storage_byte_cost()),
+            max: None
+        }
     }
 
     // ===============================================
-    fn assert_initialized(&self) {
+    /// The yoctoNEAR for the fee and for one USD at the oracle price,
+    /// if the price is fresh and the flight was not changed since `accept`
+    fn get_accept_price(&self, key: &(AccountId, FlightId), fee: Balance) -> Result<(Balance, Balance), String> {
+        let price_data: PriceData = match env::promise_result(0) {
+            PromiseResult::Successful(data) => {
+                serde_json::from_slice(&data).map_err(|_| "Cannot read the price data".to_string())?
+            },
+            _ => {
+                return Err("Cannot get the price from the oracle".to_string());
+            }
+        };
+
+        let oracle = self.price_oracle.as_ref().ok_or("The price oracle is not set")?;
+        let age = env::block_timestamp().saturating_sub(price_data.timestamp.0);
+        if age > oracle.get_max_price_age().saturating_mul(1_000_000_000) {
+            return Err("The oracle price is too old".to_string());
+        }
+        let price = price_data.prices
+            .iter()
+            .find(|asset| asset.asset_id == *oracle.get_asset_id())
+            .and_then(|asset| asset.price.as_ref())
+            .ok_or("The oracle has no price for NEAR")?;
+
+        match self.user_flights.get(key) {
+            Some(flight) if *flight.get_state() == FlightState::Idle
+                && self.get_pending_inspection(&flight).is_none()
+                && self.get_flight_fee(key, &flight) == fee => {},
+            _ => {
+                return Err("Your flight was changed during the payment".to_string());
+            }
+        }
+
+        match (price.to_yocto(fee), price.to_yocto(1)) {
+            (Some(required), Some(rate)) => Ok((required, rate)),
+            _ => Err("Cannot convert the fee".to_string())
+        }
+    }
+
+    /// The distance of the route of the flight when it is set,
+    /// else the one given by the customer
+    fn get_flight_distance(&self, flight_id: FlightId, distance: Distance) -> Distance {
+        match self.get_flight(flight_id).get_route() {
+            Some((origin, destination)) => {
+                self.get_distance(origin.clone(), destination.clone())
+            },
+            None => distance
+        }
+    }
+
+    /// Once all the baggages are claimed, the airlines are credited
+    /// and withdraw their earnings later
+    fn settle_claim(&mut self, key: &(AccountId, FlightId), flight: &mut FlightDetail) {
+        flight.set_state(FlightState::Claimed);
+        self.user_flights.insert(key,flight);
+        self.update_group_state(key, flight);
+
+        let escrow = flight.get_escrow().as_ref().expect("Cannot find the deposit");
+        for (airline_id, amount) in self.apportion_airlines(flight, escrow.get_amount()) {
+            self.credit_airline(airline_id, escrow.get_token_id(), amount);
+        }
+    }
+
+    /// The amount of each airline of the flight,
+    /// split by the distance of their segments
+    fn apportion_airlines(&self, flight: &FlightDetail, amount: Balance) -> Vec<(AirlineId, Balance)> {
+        let mut shares: Vec<(AirlineId, Balance)> = Vec::new();
+        let segments = flight.get_segments();
+        for (segment, share) in segments.iter().zip(apportion(amount, segments)) {
+            let airline_id = *self.get_flight(segment.flight_id).get_airline_id();
+            match shares.iter_mut().find(|(id, _)| *id == airline_id) {
+                Some((_, total)) => *total += share,
+                None => shares.push((airline_id, share))
+            }
+        }
+        shares
+    }
+
+    /// Credits the share of a claim to the airline, minus the commission
+    fn credit_airline(&mut self, airline_id: AirlineId, token_id: &Option<AccountId>, amount: Balance) {
+        let commission = apply_basis_points(amount, self.commission);
+        if commission > 0 {
+            let total = self.commissions.get(token_id).unwrap_or(0);
+            self.commissions.insert(token_id, &(total + commission));
+        }
+        let balance_key = &(airline_id, token_id.clone());
+        let balance = self.airline_balances.get(balance_key).unwrap_or(0);
+        self.airline_balances.insert(
+            balance_key,
+            &(balance + amount - commission)
+        );
+        self.record_settlement(airline_id, token_id, amount, commission);
+    }
+
+    /// Adds a claim to the settlement of the current period
+    fn record_settlement(
+        &mut self,
+        airline_id: AirlineId,
+        token_id: &Option<AccountId>,
+        amount: Balance,
+        commission: Balance
+    ) {
+        let period = current_period();
+        let mut settlements = self.settlements.get(&(airline_id, period)).unwrap_or_default();
+        let index = match settlements
+            .iter()
+            .position(|settlement| settlement.get_token_id() == token_id)
+        {
+            Some(index) => index,
+            None => {
+                settlements.push(Settlement::new(period, token_id.clone()));
+                settlements.len() - 1
+            }
+        };
+        settlements[index].add_claim(amount, commission);
+        self.settlements.insert(&(airline_id, period), &settlements);
+    }
+
+    /// The rules of the contract, with the tier of the customer with the airline of the flight
+    fn get_fee_policy(&self, key: &(AccountId, FlightId), flight_class: &FlightClass) -> FeePolicy {
+        let airline_id = *self.get_flight(key.1).get_airline_id();
+        FeePolicy {
+            surcharge: self.surcharge_policies.get(flight_class),
+            kind_rules: self.kind_rules.to_vec(),
+            tier: self.loyalty_tiers
+                .get(&(airline_id, key.0.clone()))
+                .map(|tier| (tier, self.get_tier_benefit(airline_id, tier)))
+        }
+    }
+
+    fn get_flight_fee(&self, key: &(AccountId, FlightId), flight: &FlightDetail) -> Balance {
+        self.get_flight_quote(key, flight).total.0
+    }
+
+    /// The fee of the flight in USD, with the rules of the contract.
+    /// A group pays for the baggages of every member at once, with the tier of its leader
+    fn get_flight_quote(&self, key: &(AccountId, FlightId), flight: &FlightDetail) -> FeeQuote {
+        match self.get_flight_group(flight) {
+            Some(group) => {
+                let leader_key = &(group.get_leader().clone(), *group.get_flight_id());
+                let leader = self.user_flights.get(leader_key).unwrap();
+                leader.get_pooled_quote(
+                    &self.get_group_baggages(&group),
+                    group.get_members().len(),
+                    &self.get_fee_policy(leader_key, leader.get_flight_class())
+                )
+            },
+            None => flight.get_quote(&self.get_fee_policy(key, flight.get_flight_class()))
+        }
+    }
+
+    fn get_flight_group(&self, flight: &FlightDetail) -> Option<Group> {
+        flight
+            .get_group_id()
+            .map(|group_id| self.groups.get(&group_id).expect("Cannot find the group"))
+    }
+
+    /// The baggages of every member, as saved in their flight
+    fn get_group_baggages(&self, group: &Group) -> Vec<Baggage> {
+        group
+            .get_keys()
+            .iter()
+            .flat_map(|key| self.user_flights.get(key).unwrap().get_baggages().values_as_vector().to_vec())
+            .collect()
+    }
+
+    /// The members of a group follow the state and the claim code of the flight
+    fn update_group_state(&mut self, key: &(AccountId, FlightId), flight: &FlightDetail) {
+        if let Some(group) = self.get_flight_group(flight) {
+            for member_key in group.get_keys().iter().filter(|member_key| *member_key != key) {
+                let mut member_flight = self.user_flights.get(member_key).unwrap();
+                member_flight.set_state(*flight.get_state());
+                member_flight.set_claim_code_hash(*flight.get_claim_code_hash());
+                self.user_flights.insert(member_key, &member_flight);
+            }
+        }
+    }
+
+    /// Sends the amount in the token, or in NEAR when there is no token
+    fn pay(receiver_id: AccountId, token_id: &Option<AccountId>, amount: Balance) -> Promise {
+        match token_id {
+            Some(token_id) => {
+                ext_ft::ft_transfer(
+                    receiver_id,
+                    U128(amount),
+                    None,
+                    token_id,
+                    1,
+                    GAS_FOR_FT_TRANSFER
+                )
+            },
+            None => {
+                Promise::new(receiver_id).transfer(amount)
+            }
+        }
+    }
+
+    /// Sends a refund, which is recorded as owed if it fails
+    fn pay_refund(account_id: AccountId, token_id: Option<AccountId>, amount: Balance) -> Promise {
+        Self::pay(account_id.clone(), &token_id, amount).then(ext_self::on_refund(
+            account_id,
+            token_id,
+            U128(amount),
+            &env::current_account_id(),
+            0,
+            GAS_FOR_PAYOUT_CALLBACK
+        ))
+    }
+
+    /// The whole deposit goes back, no commission is taken
+    fn refund_escrow(escrow: &Escrow) {
+        Self::pay_refund(
+            escrow.get_payer().clone(),
+            escrow.get_token_id().clone(),
+            escrow.get_amount()
+        );
+    }
+
+    /// The deposit is neither paid out nor refunded yet
+    fn holds_escrow(flight: &FlightDetail) -> bool {
+        flight.get_escrow().is_some() && matches!(
+            flight.get_state(),
+            FlightState::Checked | FlightState::AwaitingTopUp | FlightState::Delivered
+        )
+    }
+
+    fn is_promise_success() -> bool {
         assert_eq!(
-            self.initialized,
-            true,
-            "Contract was not initialized"
-        );  
+            env::promise_results_count(),
+            1,
+            "Expected one promise result"
+        );
+        matches!(env::promise_result(0), PromiseResult::Successful(_))
+    }
+
+    /// Charges the storage used since `initial_storage` to the account,
+    /// or gives it back when the storage was freed
+    fn update_storage(&mut self, account_id: &AccountId, initial_storage: StorageUsage) {
+        let mut account = self.storage_accounts
+            .get(account_id)
+            .expect("You must pay for storage first, see storage_deposit");
+
+        let current_storage = env::storage_usage();
+        if current_storage > initial_storage {
+            account.add_usage(current_storage - initial_storage);
+        } else {
+            account.release_usage(initial_storage - current_storage);
+        }
+        self.storage_accounts.insert(account_id, &account);
+    }
+
+    /// The bytes for registering the longest possible account
+    fn measure_account_storage_usage(&mut self) {
+        let initial_storage = env::storage_usage();
+        let account_id = "a".repeat(64);
+        self.storage_accounts.insert(&account_id, &StorageAccount::new(0, 0));
+        self.account_storage_usage = env::storage_usage() - initial_storage;
+        self.storage_accounts.remove(&account_id);
+    }
+
+    fn assert_not_paused(&self) {
+        assert!(
+            !self.paused,
+            "The contract is paused"
+        );
+    }
+
+    fn assert_role(&self, role: Role, airline_id: Option<AirlineId>, message: &str) {
+        if !self.has_role(env::predecessor_account_id(), role, airline_id) {
+            panic!("{}", message);
+        }
+    }
+
+    fn assert_can_manage_role(&self, role: Role, airline_id: Option<AirlineId>) {
+        assert_ne!(
+            role,
+            Role::Owner,
+            "The owner role is transferred by propose_owner"
+        );
+        if role.is_airline_role() {
+            let airline_id = airline_id.expect("Airline roles need an airline");
+            self.get_airline(airline_id);
+        } else {
+            assert!(
+                airline_id.is_none(),
+                "{:?} is not an airline role", role
+            );
+        }
+
+        let predecessor = env::predecessor_account_id();
+        if predecessor == self.owner {
+            return;
+        }
+        match role {
+            Role::CheckInAgent | Role::GroundHandler => {
+                self.assert_role(
+                    Role::AirlineAdmin,
+                    airline_id,
+                    "Only the owner or the airline admins can manage this role"
+                );
+            },
+            _ => {
+                panic!("Only the owner can manage this role");
+            }
+        }
+    }
+
+    fn assert_payment_account(payment_account: &AccountId) {
+        // Payment account (Airlines) must not same as contract account
+        assert_ne!(
+            *payment_account,
+            env::current_account_id(),
+            "Payment account cannot be contract"
+        );
+
+        assert!(
+            env::is_valid_account_id(payment_account.as_bytes()),
+            "Payment account is invalid"
+        );
+    }
+
+    /// Checks a new baggage against the rules of the contract,
+    /// then adds it and charges its storage to the customer
+    fn insert_baggage(
+        &mut self,
+        key: &(AccountId, FlightId),
+        flight: &mut FlightDetail,
+        baggage_weight: Weight,
+        dimensions: Option<Dimensions>,
+        kind: Option<BaggageKind>,
+        dangerous_goods: Option<Vec<DeclaredItem>>
+    ) -> BaggageId {
+        match self.get_flight_group(flight) {
+            Some(group) => {
+                let baggages = self.get_group_baggages(&group);
+                self.get_allowance(key.1, *flight.get_flight_class())
+                    .pooled(group.get_members().len())
+                    .assert_baggage(
+                        baggages.len() as u64,
+                        baggages.iter().map(|baggage| *baggage.get_billed_weight()).sum(),
+                        baggage_weight
+                    );
+            },
+            None => {
+                self.get_allowance(key.1, *flight.get_flight_class()).assert_baggage(
+                    flight.get_baggages().len(),
+                    flight.get_total_weight(),
+                    baggage_weight
+                );
+            }
+        }
+        if let Some(dimensions) = &dimensions {
+            dimensions.assert_valid();
+        }
+        let kind = kind.unwrap_or_default();
+        self.get_kind_rule(kind).assert_baggage(
+            kind,
+            flight.count_kind(kind),
+            baggage_weight
+        );
+        let dangerous_goods = dangerous_goods.unwrap_or_default();
+        let inspection = check_declaration(&dangerous_goods);
+
+        let baggage_id: BaggageId = self.count_baggage;
+        let new_baggage = Baggage::new (
+            baggage_id,
+            baggage_weight,
+            dimensions,
+            kind,
+            dangerous_goods,
+            inspection
+        );
+        // Without dimensions, a baggage would never be oversize
+        assert!(
+            new_baggage.get_dimensions().is_some()
+                || self.get_fee_policy(key, flight.get_flight_class())
+                    .get_surcharge_policy(&new_baggage)
+                    .is_none(),
+            "The baggage dimensions are required in {:?} class", flight.get_flight_class()
+        );
+
+        let initial_storage = env::storage_usage();
+        flight.add_baggage(
+            new_baggage
+        );
+        self.user_flights.insert(key,flight);
+        self.update_storage(&key.0, initial_storage);
+        self.count_baggage += 1;
+
+        env::log("Add baggage succesfully".as_bytes());
+        env::log(format!("Baggage id: {}",&baggage_id).as_bytes());
+        if inspection == InspectionState::Pending {
+            env::log("This baggage must be inspected by the airline before the payment".as_bytes());
+        }
+
+        baggage_id
+    }
+
+    /// Settles a paid flight against its new fee
+    fn reconcile(&mut self, key: &(AccountId, FlightId), flight: &mut FlightDetail, fee: Balance) {
+        let mut escrow = flight.get_escrow().clone().expect("Cannot find the deposit");
+        if fee > escrow.get_fee() {
+            flight.set_state(FlightState::AwaitingTopUp);
+            env::log(format!(
+                "{} must top up {} for flight {}",
+                key.0,
+                escrow.get_top_up(fee),
+                key.1
+            ).as_bytes());
+            return;
+        }
+
+        let released = escrow.release(fee);
+        if released > 0 {
+            Self::pay_refund(
+                escrow.get_payer().clone(),
+                escrow.get_token_id().clone(),
+                released
+            );
+        }
+        flight.set_escrow(escrow);
+        flight.set_state(FlightState::Checked);
+    }
+
+    /// Settles the deposit of the flight, which is held by the leader of a group
+    fn reconcile_group(&mut self, key: &(AccountId, FlightId), flight: &mut FlightDetail) {
+        let fee = self.get_flight_fee(key, flight);
+        match self.get_flight_group(flight) {
+            Some(group) if !group.is_leader(&key.0) => {
+                let leader_key = &(group.get_leader().clone(), key.1);
+                let mut leader = self.user_flights.get(leader_key).unwrap();
+                self.reconcile(leader_key, &mut leader, fee);
+                self.user_flights.insert(leader_key, &leader);
+                self.update_group_state(leader_key, &leader);
+                flight.set_state(*leader.get_state());
+            },
+            _ => {
+                self.reconcile(key, flight, fee);
+                self.update_group_state(key, flight);
+            }
+        }
+    }
+
+    /// The leader of a group pays, and is paid back, for every member
+    fn assert_payer(&self, key: &(AccountId, FlightId), flight: &FlightDetail) {
+        if let Some(group) = self.get_flight_group(flight) {
+            if !group.is_leader(&key.0) {
+                panic!("The leader {} of the group pays for your baggages", group.get_leader());
+            }
+        }
+    }
+
+    /// The leader settles the claim of the whole group,
+    /// so the baggages of the members are picked up through the leader
+    fn assert_group_claimer(&self, key: &(AccountId, FlightId), flight: &FlightDetail) {
+        if let Some(group) = self.get_flight_group(flight) {
+            if !group.is_leader(&key.0) {
+                panic!("The leader {} of the group claims the baggages of {}", group.get_leader(), key.0);
+            }
+        }
+    }
+
+    /// Another account pays only with the approval of the customer
+    fn assert_sponsor(key: &(AccountId, FlightId), flight: &FlightDetail, payer: &AccountId) {
+        assert!(
+            *payer == key.0 || flight.get_sponsor().as_ref() == Some(payer),
+            "{} is not approved to pay for {}", payer, key.0
+        );
+    }
+
+    /// A top up joins the deposit, which is refunded to its payer
+    fn assert_escrow_payer(escrow: &Escrow, account_id: &AccountId) {
+        assert!(
+            escrow.get_payer() == account_id,
+            "Only {} who paid the deposit can top it up", escrow.get_payer()
+        );
+    }
 
+    /// Only a flight without connections can be in a group
+    fn assert_poolable(flight: &FlightDetail) {
+        assert!(
+            flight.get_group_id().is_none(),
+            "Your flight is already in a group"
+        );
+        assert!(
+            flight.get_segments().len() == 1,
+            "An itinerary cannot be pooled"
+        );
+    }
+
+    /// The first baggage of the flight, or of its group, to inspect
+    fn get_pending_inspection(&self, flight: &FlightDetail) -> Option<BaggageId> {
+        match self.get_flight_group(flight) {
+            Some(group) => group
+                .get_keys()
+                .iter()
+                .find_map(|key| self.user_flights.get(key).unwrap().get_pending_inspection()),
+            None => flight.get_pending_inspection()
+        }
+    }
+
+    fn assert_inspected(&self, flight: &FlightDetail) {
+        if let Some(baggage_id) = self.get_pending_inspection(flight) {
+            panic!("Baggage {} must be inspected by the airline first", baggage_id);
+        }
     }
 
     fn assert_state(&self, flight: &FlightDetail, target_state: FlightState) {
@@ -0,0 +1,51 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{serde::{Serialize, Deserialize}};
+use crate::types::*;
+
+/// The frequent-flyer tier of an account, set by each airline
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoyaltyTier {
+    Silver,
+    Gold,
+    Platinum
+}
+
+/// What a tier takes off the fee of the class strategy:
+/// first the free baggages, then the discount on the rest
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TierBenefit {
+    pub discount: BasisPoints,
+    pub free_baggages: u8
+}
+
+impl TierBenefit {
+    pub fn default_for(tier: LoyaltyTier) -> Self {
+        match tier {
+            LoyaltyTier::Silver => Self {
+                discount: 1_000,
+                free_baggages: 0
+            },
+            LoyaltyTier::Gold => Self {
+                discount: 0,
+                free_baggages: 1
+            },
+            LoyaltyTier::Platinum => Self {
+                discount: 2_000,
+                free_baggages: 1
+            }
+        }
+    }
+
+    pub fn assert_valid(&self) {
+        assert!(
+            self.discount <= MAX_BASIS_POINTS,
+            "The discount cannot be more than {} basis points", MAX_BASIS_POINTS
+        );
+    }
+}
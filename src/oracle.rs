@@ -0,0 +1,72 @@
+use near_sdk::{AccountId, Balance};
+use near_sdk::json_types::{U64, U128};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};  
+use near_sdk::{serde::{Serialize, Deserialize}};
+
+/// Where the NEAR price in USD is read from
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Debug, PartialEq)]
+pub struct PriceOracle {
+    oracle_id: AccountId,
+    asset_id: String,
+    max_price_age: u64      // seconds
+}
+
+impl PriceOracle {
+    pub fn new(oracle_id: AccountId, asset_id: String, max_price_age: u64) -> Self {
+        Self {
+            oracle_id,
+            asset_id,
+            max_price_age
+        }
+    }
+
+    pub fn get_oracle_id(&self) -> &AccountId {
+        &self.oracle_id
+    }
+
+    pub fn get_asset_id(&self) -> &String {
+        &self.asset_id
+    }
+
+    pub fn get_max_price_age(&self) -> &u64 {
+        &self.max_price_age
+    }
+}
+
+// The price oracle interface, see https://github.com/NearDeFi/price-oracle
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Price {
+    pub multiplier: U128,
+    pub decimals: u8
+}
+
+impl Price {
+    /// The yoctoNEAR worth `usd` dollars, rounded up.
+    /// One yoctoNEAR is worth `multiplier / 10^decimals` dollars
+    pub fn to_yocto(&self, usd: Balance) -> Option<Balance> {
+        if self.multiplier.0 == 0 {
+            return None;
+        }
+        let value = usd.checked_mul(10u128.checked_pow(self.decimals as u32)?)?;
+        Some(value.div_ceil(self.multiplier.0))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetOptionalPrice {
+    pub asset_id: String,
+    pub price: Option<Price>
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceData {
+    pub timestamp: U64,     // nanoseconds
+    pub recency_duration_sec: u32,
+    pub prices: Vec<AssetOptionalPrice>
+}
@@ -0,0 +1,50 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};  
+use near_sdk::{serde::{Serialize, Deserialize}};
+use crate::types::*;
+
+#[derive(Serialize, Deserialize, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Role {
+    Owner,
+    AirlineAdmin,
+    CheckInAgent,
+    GroundHandler,
+    Arbiter
+}
+
+impl Role {
+    /// Airline roles are granted for a single airline,
+    /// the others apply to the whole contract
+    pub fn is_airline_role(&self) -> bool {
+        match self {
+            Role::AirlineAdmin | Role::CheckInAgent | Role::GroundHandler => true,
+            Role::Owner | Role::Arbiter => false
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Debug, PartialEq)]
+pub struct RoleGrant {
+    role: Role,
+    airline_id: Option<AirlineId>
+}
+
+impl RoleGrant {
+    pub fn new(role: Role, airline_id: Option<AirlineId>) -> Self {
+        Self {
+            role,
+            airline_id
+        }
+    }
+
+    pub fn get_role(&self) -> &Role {
+        &self.role
+    }
+
+    pub fn get_airline_id(&self) -> &Option<AirlineId> {
+        &self.airline_id
+    }
+}
@@ -0,0 +1,58 @@
+use near_sdk::Balance;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{serde::{Serialize, Deserialize}};
+use crate::types::*;
+
+/// The connections an itinerary can have
+pub const MAX_SEGMENTS: usize = 4;
+
+/// A leg of an itinerary, the baggages are transferred to the next leg
+/// by the ground handlers of its airline
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Segment {
+    pub flight_id: FlightId,
+    pub distance: Distance,
+    #[serde(default)]
+    pub transferred: bool
+}
+
+impl Segment {
+    pub fn new(flight_id: FlightId, distance: Distance) -> Self {
+        Self {
+            flight_id,
+            distance,
+            transferred: false
+        }
+    }
+}
+
+/// Splits `amount` by the distance of the segments,
+/// the rounding goes to the last one
+pub fn apportion(amount: Balance, segments: &[Segment]) -> Vec<Balance> {
+    // Distances are weighted in 1/1000 miles to stay in integers
+    let weights: Vec<Balance> = segments
+        .iter()
+        .map(|segment| (segment.distance.max(0.0) * 1000.0) as Balance)
+        .collect();
+    let total: Balance = weights.iter().sum();
+    if total == 0 {
+        let mut shares = vec![0; segments.len()];
+        if let Some(last) = shares.last_mut() {
+            *last = amount;
+        }
+        return shares;
+    }
+
+    let mut shares: Vec<Balance> = weights
+        .iter()
+        .map(|weight| amount / total * weight + amount % total * weight / total)
+        .collect();
+    let rest = amount - shares.iter().sum::<Balance>();
+    if let Some(last) = shares.last_mut() {
+        *last += rest;
+    }
+    shares
+}
@@ -0,0 +1,73 @@
+use near_sdk::{env, AccountId, Balance};
+use near_sdk::json_types::U128;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{serde::{Serialize, Deserialize}};
+
+/// The length of a settlement period, one day
+pub const SETTLEMENT_PERIOD: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// The periods a settlement report can cover at once
+pub const MAX_SETTLEMENT_PERIODS: u64 = 366;
+
+pub type SettlementPeriod = u64;
+
+/// The period of the current block, counted in days since the Unix epoch
+pub fn current_period() -> SettlementPeriod {
+    env::block_timestamp() / SETTLEMENT_PERIOD
+}
+
+/// The claims credited to an airline in one period, for one token
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settlement {
+    period: SettlementPeriod,
+    token_id: Option<AccountId>,
+    claims: u64,
+    gross: U128,
+    commission: U128
+}
+
+impl Settlement {
+    pub fn new(period: SettlementPeriod, token_id: Option<AccountId>) -> Self {
+        Self {
+            period,
+            token_id,
+            claims: 0,
+            gross: U128(0),
+            commission: U128(0)
+        }
+    }
+
+    pub fn get_period(&self) -> &SettlementPeriod {
+        &self.period
+    }
+
+    pub fn get_token_id(&self) -> &Option<AccountId> {
+        &self.token_id
+    }
+
+    pub fn get_claims(&self) -> &u64 {
+        &self.claims
+    }
+
+    pub fn get_gross(&self) -> Balance {
+        self.gross.0
+    }
+
+    pub fn get_commission(&self) -> Balance {
+        self.commission.0
+    }
+
+    /// The amount credited to the airline
+    pub fn get_net(&self) -> Balance {
+        self.gross.0 - self.commission.0
+    }
+
+    pub fn add_claim(&mut self, amount: Balance, commission: Balance) {
+        self.claims += 1;
+        self.gross = U128(self.gross.0 + amount);
+        self.commission = U128(self.commission.0 + commission);
+    }
+}
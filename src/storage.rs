@@ -0,0 +1,88 @@
+use near_sdk::{env, Balance, StorageUsage};
+use near_sdk::json_types::U128;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};  
+use near_sdk::{serde::{Serialize, Deserialize}};
+
+/// The storage deposit of an account and the bytes it uses
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StorageAccount {
+    deposit: Balance,
+    usage: StorageUsage
+}
+
+impl StorageAccount {
+    pub fn new(deposit: Balance, usage: StorageUsage) -> Self {
+        Self {
+            deposit,
+            usage
+        }
+    }
+
+    pub fn get_deposit(&self) -> &Balance {
+        &self.deposit
+    }
+
+    pub fn get_usage(&self) -> &StorageUsage {
+        &self.usage
+    }
+
+    pub fn get_locked(&self) -> Balance {
+        Balance::from(self.usage) * env::storage_byte_cost()
+    }
+
+    pub fn get_available(&self) -> Balance {
+        self.deposit.saturating_sub(self.get_locked())
+    }
+
+    pub fn add_deposit(&mut self, amount: Balance) {
+        self.deposit += amount;
+    }
+
+    pub fn withdraw(&mut self, amount: Balance) {
+        assert!(
+            amount <= self.get_available(),
+            "Cannot withdraw more than {} yoctoNEAR", self.get_available()
+        );
+        self.deposit -= amount;
+    }
+
+    pub fn can_add_usage(&self, bytes: StorageUsage) -> bool {
+        Balance::from(self.usage + bytes) * env::storage_byte_cost() <= self.deposit
+    }
+
+    pub fn add_usage(&mut self, bytes: StorageUsage) {
+        self.usage += bytes;
+        assert!(
+            self.get_locked() <= self.deposit,
+            "Not enough storage deposit, {} yoctoNEAR more is needed",
+            self.get_locked() - self.deposit
+        );
+    }
+
+    pub fn release_usage(&mut self, bytes: StorageUsage) {
+        self.usage = self.usage.saturating_sub(bytes);
+    }
+
+    pub fn to_balance(&self) -> StorageBalance {
+        StorageBalance {
+            total: U128(self.deposit),
+            available: U128(self.get_available())
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Debug, PartialEq)]
+pub struct StorageBalance {
+    pub total: U128,
+    pub available: U128
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Debug, PartialEq)]
+pub struct StorageBalanceBounds {
+    pub min: U128,
+    pub max: Option<U128>
+}
@@ -0,0 +1,74 @@
+use near_sdk::Balance;
+use near_sdk::json_types::U128;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{serde::{Serialize, Deserialize}};
+use crate::baggage::*;
+use crate::types::*;
+
+/// The thresholds of a class above which a baggage pays a surcharge (in USD)
+#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SurchargePolicy {
+    pub max_linear_dimension: Length,
+    pub oversize_surcharge: U128,
+    pub max_weight: Weight,
+    pub overweight_surcharge: U128
+}
+
+impl SurchargePolicy {
+    pub fn assert_valid(&self) {
+        assert!(
+            self.max_linear_dimension > 0.0 && self.max_weight > 0.0,
+            "Surcharge thresholds must be positive"
+        );
+    }
+
+    /// Baggages without dimensions are never oversize,
+    /// they are only added where no policy applies to them
+    pub fn is_oversize(&self, baggage: &Baggage) -> bool {
+        baggage
+            .get_linear_dimension()
+            .is_some_and(|linear| linear > self.max_linear_dimension)
+    }
+
+    pub fn is_overweight(&self, baggage: &Baggage) -> bool {
+        *baggage.get_billed_weight() > self.max_weight
+    }
+
+    pub fn get_surcharge(&self, baggage: &Baggage) -> Balance {
+        let mut surcharge = 0;
+        if self.is_oversize(baggage) {
+            surcharge += self.oversize_surcharge.0;
+        }
+        if self.is_overweight(baggage) {
+            surcharge += self.overweight_surcharge.0;
+        }
+        surcharge
+    }
+}
+
+/// The classification of a baggage under the policy of its class
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[derive(Debug, PartialEq)]
+pub struct BaggageSurcharge {
+    pub baggage_id: BaggageId,
+    pub linear_dimension: Option<Length>,
+    pub oversize: bool,
+    pub overweight: bool,
+    pub surcharge: U128
+}
+
+impl BaggageSurcharge {
+    pub fn new(baggage: &Baggage, policy: Option<&SurchargePolicy>) -> Self {
+        Self {
+            baggage_id: *baggage.get_id(),
+            linear_dimension: baggage.get_linear_dimension(),
+            oversize: policy.is_some_and(|policy| policy.is_oversize(baggage)),
+            overweight: policy.is_some_and(|policy| policy.is_overweight(baggage)),
+            surcharge: U128(policy.map_or(0, |policy| policy.get_surcharge(baggage)))
+        }
+    }
+}
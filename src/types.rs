@@ -2,9 +2,20 @@ use near_sdk::Balance;
 
 pub type BaggageId = u64;
 pub type FlightId = u64;
+pub type AirlineId = u64;
+pub type GroupId = u64;
 pub type Distance = f32;    // miles    
 pub type Weight = f32;      // pound (lbs)
+pub type Length = f32;      // inch
+pub type BasisPoints = u16; // 1/100 of a percent
+
+pub const MAX_BASIS_POINTS: BasisPoints = 10_000;
 
 pub fn to_yoto(near: Balance) -> Balance {
-    return near*10u128.pow(24);
-}
\ No newline at end of file
+    near*10u128.pow(24)
+}
+/// The part of `amount` taken at `rate`, rounded down
+pub fn apply_basis_points(amount: Balance, rate: BasisPoints) -> Balance {
+    amount / Balance::from(MAX_BASIS_POINTS) * Balance::from(rate)
+        + amount % Balance::from(MAX_BASIS_POINTS) * Balance::from(rate) / Balance::from(MAX_BASIS_POINTS)
+}
@@ -0,0 +1,386 @@
+#[cfg(test)]
+mod sim {
+    use airplane_baggage_checking::*;
+    use airplane_baggage_checking::flight_detail::*;
+    use airplane_baggage_checking::types::*;
+    use airplane_baggage_checking::role::*;
+    use mock_ft::MockFt;
+    use mock_oracle::{MockOracle, Price};
+    use near_sdk::{MockedBlockchain, Balance, PromiseOrValue, PromiseResult};
+    use near_sdk::json_types::{U128, Base58CryptoHash};
+    use near_sdk::{testing_env, VMContext, env};
+    use near_sdk::test_utils::get_created_receipts;
+    use near_sdk::serde::de::DeserializeOwned;
+    use near_sdk::serde_json::{self, json, Value};
+    use std::convert::TryInto;
+    use std::panic::{self, AssertUnwindSafe};
+
+    const CONTRACT: &str = "baggage.testnet";
+    const TOKEN: &str = "usdc.testnet";
+    const ORACLE: &str = "oracle.testnet";
+    const AIRLINE: &str = "phuc16102001.testnet";
+    const CUSTOMER: &str = "thanhhoang4869.testnet";
+    const CLAIM_CODE: &str = "7KQ2-M9XD-4FZL";
+
+    // 1 USD is 10^6 units of the token
+    const USD: Balance = 1_000_000;
+    // 1 yoctoNEAR is worth multiplier / 10^28 USD
+    const ONE_USD_PER_NEAR: Balance = 10_000;
+
+    /// The contract and the contracts it calls. Each call runs in its own context,
+    /// then the receipts it created run on their receiver like on chain:
+    /// a callback gets the result of the call it waits for
+    struct Sim {
+        contract: Contract,
+        token: MockFt,
+        oracle: MockOracle,
+        block_timestamp: u64,
+        /// The NEAR sent by the contracts, in order
+        transfers: Vec<(String, f64)>
+    }
+
+    fn get_context(
+        predecessor_account_id: &str,
+        current_account_id: &str,
+        attached_deposit: Balance,
+        block_timestamp: u64
+    ) -> VMContext {
+        VMContext {
+            current_account_id: current_account_id.to_string(),
+            signer_account_id: predecessor_account_id.to_string(),
+            signer_account_pk: vec![0, 1, 2],
+            predecessor_account_id: predecessor_account_id.to_string(),
+            input: vec![],
+            block_index: 0,
+            block_timestamp,
+            account_balance: 10u128.pow(32),
+            account_locked_balance: 0,
+            storage_usage: 10u64.pow(6),
+            attached_deposit,
+            prepaid_gas: 10u64.pow(18),
+            random_seed: vec![0, 1, 2],
+            is_view: false,
+            output_data_receivers: vec![],
+            epoch_height: 19,
+        }
+    }
+
+    fn arg<T: DeserializeOwned>(args: &Value, name: &str) -> T {
+        serde_json::from_value(args[name].clone())
+            .unwrap_or_else(|_| panic!("Cannot read the argument {}", name))
+    }
+
+    fn to_result(value: Option<Vec<u8>>) -> PromiseResult {
+        match value {
+            Some(value) => PromiseResult::Successful(value),
+            None => PromiseResult::Failed
+        }
+    }
+
+    impl Sim {
+        fn new() -> Self {
+            testing_env!(get_context(AIRLINE, CONTRACT, 0, 0));
+            let mut contract = Contract::new(AIRLINE.to_string());
+            let airline_id = contract.add_airline("VN".to_string(), AIRLINE.to_string());
+            for role in [Role::AirlineAdmin, Role::GroundHandler, Role::Arbiter].iter() {
+                let airline = if *role == Role::Arbiter { None } else { Some(airline_id) };
+                contract.grant_role(AIRLINE.to_string(), *role, airline);
+            }
+            contract.add_flight(airline_id, 1);
+            contract.add_token(TOKEN.to_string(), 6);
+            contract.set_price_oracle(ORACLE.to_string(), "wrap.testnet".to_string(), 90);
+
+            testing_env!(get_context(CUSTOMER, TOKEN, 0, 0));
+            let mut token = MockFt::new(CUSTOMER.to_string(), U128(10 * USD));
+            token.register(CONTRACT.to_string());
+            token.register(AIRLINE.to_string());
+
+            testing_env!(get_context(AIRLINE, ORACLE, 0, 0));
+            let oracle = MockOracle::new();
+
+            let mut sim = Self {
+                contract,
+                token,
+                oracle,
+                block_timestamp: 0,
+                transfers: Vec::new()
+            };
+            sim.call_contract(CUSTOMER, to_yoto(1), |contract| {
+                contract.storage_deposit(None, None);
+            });
+            sim.call_contract(CUSTOMER, 0, |contract| {
+                contract.registry(1, FlightClass::Business, 10.0);
+                contract.add_baggage(1, 5.0, None, None, None);
+            });
+            sim
+        }
+
+        /// Calls the contract directly, then runs the receipts it created
+        fn call_contract<R>(&mut self, predecessor: &str, deposit: Balance, f: impl FnOnce(&mut Contract) -> R) -> R {
+            testing_env!(get_context(predecessor, CONTRACT, deposit, self.block_timestamp));
+            let result = f(&mut self.contract);
+            self.run_receipts(CONTRACT);
+            result
+        }
+
+        fn call_token<R>(&mut self, predecessor: &str, deposit: Balance, f: impl FnOnce(&mut MockFt) -> R) -> R {
+            testing_env!(get_context(predecessor, TOKEN, deposit, self.block_timestamp));
+            let result = f(&mut self.token);
+            self.run_receipts(TOKEN);
+            result
+        }
+
+        fn call_oracle<R>(&mut self, predecessor: &str, f: impl FnOnce(&mut MockOracle) -> R) -> R {
+            testing_env!(get_context(predecessor, ORACLE, 0, self.block_timestamp));
+            f(&mut self.oracle)
+        }
+
+        fn run_receipts(&mut self, predecessor: &str) {
+            // Receipts hold u128 balances, which `to_value` cannot handle
+            let receipts: Value = serde_json::from_str(
+                &serde_json::to_string(&get_created_receipts()).unwrap()
+            ).unwrap();
+
+            let mut results: Vec<Option<Vec<u8>>> = Vec::new();
+            for receipt in receipts.as_array().unwrap() {
+                let receiver = receipt["receiver_id"].as_str().unwrap().to_string();
+                let promise_results: Vec<Option<Vec<u8>>> = receipt["receipt_indices"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|index| results[index.as_u64().unwrap() as usize].clone())
+                    .collect();
+
+                let mut result = Some(vec![]);
+                for action in receipt["actions"].as_array().unwrap() {
+                    if let Some(transfer) = action.get("Transfer") {
+                        self.transfers.push((receiver.clone(), transfer["deposit"].as_f64().unwrap()));
+                    } else if let Some(call) = action.get("FunctionCall") {
+                        let args = call["args"].as_str().unwrap();
+                        let args = if args.is_empty() { json!({}) } else { serde_json::from_str(args).unwrap() };
+                        result = self.run_call(
+                            predecessor,
+                            &receiver,
+                            call["method_name"].as_str().unwrap(),
+                            &args,
+                            call["deposit"].as_f64().unwrap() as Balance,
+                            promise_results.clone()
+                        );
+                    }
+                }
+                results.push(result);
+            }
+        }
+
+        /// Returns the JSON result of the call, `None` when it failed.
+        /// The receipts of a failed call are dropped
+        fn run_call(
+            &mut self,
+            predecessor: &str,
+            receiver: &str,
+            method: &str,
+            args: &Value,
+            deposit: Balance,
+            promise_results: Vec<Option<Vec<u8>>>
+        ) -> Option<Vec<u8>> {
+            testing_env!(
+                get_context(predecessor, receiver, deposit, self.block_timestamp),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+                promise_results.into_iter().map(to_result).collect()
+            );
+            let result = panic::catch_unwind(AssertUnwindSafe(|| self.dispatch(receiver, method, args))).ok()?;
+            self.run_receipts(receiver);
+            Some(result)
+        }
+
+        fn dispatch(&mut self, receiver: &str, method: &str, args: &Value) -> Vec<u8> {
+            let result = match (receiver, method) {
+                (TOKEN, "ft_transfer") => {
+                    self.token.ft_transfer(arg(args, "receiver_id"), arg(args, "amount"), arg(args, "memo"));
+                    Value::Null
+                },
+                (TOKEN, "ft_resolve_transfer") => json!(self.token.ft_resolve_transfer(
+                    arg(args, "sender_id"),
+                    arg(args, "receiver_id"),
+                    arg(args, "amount")
+                )),
+                (CONTRACT, "ft_on_transfer") => {
+                    match self.contract.ft_on_transfer(arg(args, "sender_id"), arg(args, "amount"), arg(args, "msg")) {
+                        PromiseOrValue::Value(unused) => json!(unused),
+                        PromiseOrValue::Promise(_) => panic!("The simulation cannot wait for a promise")
+                    }
+                },
+                (ORACLE, "get_price_data") => json!(self.oracle.get_price_data(arg(args, "asset_ids"))),
+                (CONTRACT, "on_accept_price") => json!(self.contract.on_accept_price(
+                    arg(args, "customer_id"),
+                    arg(args, "payer"),
+                    arg(args, "flight_id"),
+                    arg(args, "fee"),
+                    arg(args, "deposit")
+                )),
+                (CONTRACT, "on_refund") => json!(self.contract.on_refund(
+                    arg(args, "account_id"),
+                    arg(args, "token_id"),
+                    arg(args, "amount")
+                )),
+                (CONTRACT, "on_withdraw") => json!(self.contract.on_withdraw(
+                    arg(args, "airline_id"),
+                    arg(args, "token_id"),
+                    arg(args, "amount")
+                )),
+                _ => panic!("The simulation cannot call {} on {}", method, receiver)
+            };
+            serde_json::to_vec(&result).unwrap()
+        }
+
+        fn balance_of(&self, account_id: &str) -> Balance {
+            self.token.ft_balance_of(account_id.to_string()).0
+        }
+
+        fn state(&mut self) -> String {
+            self.call_contract(CUSTOMER, 0, |contract| contract.check_state(1))
+        }
+
+        fn set_near_price(&mut self, multiplier: Balance) {
+            self.call_oracle(AIRLINE, |oracle| {
+                oracle.set_price(
+                    "wrap.testnet".to_string(),
+                    Some(Price {
+                        multiplier: U128(multiplier),
+                        decimals: 28
+                    })
+                );
+            });
+        }
+
+        fn pay_with_token(&mut self, amount: Balance) {
+            self.call_token(CUSTOMER, 1, |token| {
+                token.ft_transfer_call(
+                    CONTRACT.to_string(),
+                    U128(amount),
+                    None,
+                    json!({"flight_id": 1}).to_string()
+                );
+            });
+        }
+    }
+
+    fn get_claim_code_hash() -> Base58CryptoHash {
+        let hash: [u8; 32] = env::sha256(CLAIM_CODE.as_bytes()).try_into().unwrap();
+        hash.into()
+    }
+
+    #[test]
+    fn token_payment_paid_out_to_airline() {
+        let mut sim = Sim::new();
+
+        sim.pay_with_token(10 * USD);
+        assert_eq!(sim.state(), "Checked");
+        assert_eq!(sim.balance_of(CUSTOMER), 0);
+        assert_eq!(sim.balance_of(CONTRACT), 10 * USD);
+
+        sim.call_contract(AIRLINE, 0, |contract| {
+            contract.deliver_baggage(CUSTOMER.to_string(), 1, get_claim_code_hash());
+        });
+        sim.call_contract(CUSTOMER, 0, |contract| {
+            contract.claim_baggages(1, CLAIM_CODE.to_string());
+        });
+        sim.call_contract(AIRLINE, 0, |contract| {
+            contract.withdraw(0, Some(TOKEN.to_string()), None);
+        });
+
+        assert_eq!(sim.balance_of(CONTRACT), 0);
+        assert_eq!(sim.balance_of(AIRLINE), 10 * USD);
+        assert!(
+            sim.call_contract(AIRLINE, 0, |contract| contract.get_airline_balances(0)).is_empty()
+        );
+    }
+
+    #[test]
+    fn token_payment_wrong_amount_given_back() {
+        let mut sim = Sim::new();
+
+        sim.pay_with_token(9 * USD);
+        assert_eq!(sim.state(), "Idle");
+        assert_eq!(sim.balance_of(CUSTOMER), 10 * USD);
+        assert_eq!(sim.balance_of(CONTRACT), 0);
+    }
+
+    #[test]
+    fn token_refund_failed_then_withdrawn() {
+        let mut sim = Sim::new();
+        sim.pay_with_token(10 * USD);
+
+        // The refund cannot be received once the customer left the token
+        sim.call_token(CUSTOMER, 0, |token| token.unregister());
+        sim.call_contract(AIRLINE, 0, |contract| contract.refund(CUSTOMER.to_string(), 1));
+        assert_eq!(sim.state(), "Refunded");
+        assert_eq!(sim.balance_of(CONTRACT), 10 * USD);
+        assert_eq!(
+            sim.call_contract(CUSTOMER, 0, |contract| contract.get_owed(CUSTOMER.to_string())),
+            vec![(Some(TOKEN.to_string()), U128(10 * USD))]
+        );
+
+        sim.call_token(CUSTOMER, 0, |token| token.register(CUSTOMER.to_string()));
+        sim.call_contract(CUSTOMER, 1, |contract| {
+            contract.withdraw_owed(Some(TOKEN.to_string()));
+        });
+        assert_eq!(sim.balance_of(CUSTOMER), 10 * USD);
+        assert_eq!(sim.balance_of(CONTRACT), 0);
+        assert!(
+            sim.call_contract(CUSTOMER, 0, |contract| contract.get_owed(CUSTOMER.to_string())).is_empty()
+        );
+    }
+
+    #[test]
+    fn near_payment_converted_by_oracle() {
+        let mut sim = Sim::new();
+        sim.set_near_price(2 * ONE_USD_PER_NEAR);
+
+        // 10 USD is 5 NEAR, the excess goes back
+        sim.call_contract(CUSTOMER, to_yoto(8), |contract| {
+            contract.accept(1);
+        });
+        assert_eq!(sim.state(), "Checked");
+        assert_eq!(
+            sim.transfers,
+            vec![(CUSTOMER.to_string(), to_yoto(3) as f64)]
+        );
+        assert_eq!(
+            sim.call_contract(CUSTOMER, 0, |contract| contract.check_escrow(1)).unwrap().get_amount(),
+            to_yoto(5)
+        );
+    }
+
+    #[test]
+    fn near_payment_stale_price_given_back() {
+        let mut sim = Sim::new();
+        sim.set_near_price(ONE_USD_PER_NEAR);
+
+        sim.block_timestamp = 91 * 1_000_000_000;
+        sim.call_contract(CUSTOMER, to_yoto(10), |contract| {
+            contract.accept(1);
+        });
+        assert_eq!(sim.state(), "Idle");
+        assert_eq!(
+            sim.transfers,
+            vec![(CUSTOMER.to_string(), to_yoto(10) as f64)]
+        );
+    }
+
+    #[test]
+    fn near_payment_without_price_given_back() {
+        let mut sim = Sim::new();
+
+        sim.call_contract(CUSTOMER, to_yoto(10), |contract| {
+            contract.accept(1);
+        });
+        assert_eq!(sim.state(), "Idle");
+        assert_eq!(
+            sim.transfers,
+            vec![(CUSTOMER.to_string(), to_yoto(10) as f64)]
+        );
+    }
+}
@@ -3,8 +3,25 @@ mod tests {
     use airplane_baggage_checking::*;
     use airplane_baggage_checking::flight_detail::*;
     use airplane_baggage_checking::types::*;
-    use near_sdk::{MockedBlockchain, Balance};
-    use near_sdk::{testing_env, VMContext};
+    use airplane_baggage_checking::role::*;
+    use airplane_baggage_checking::escrow::*;
+    use airplane_baggage_checking::settlement::*;
+    use airplane_baggage_checking::allowance::*;
+    use airplane_baggage_checking::baggage::*;
+    use airplane_baggage_checking::surcharge::*;
+    use airplane_baggage_checking::kind_rule::*;
+    use airplane_baggage_checking::dangerous_goods::*;
+    use airplane_baggage_checking::segment::*;
+    use airplane_baggage_checking::airport::*;
+    use airplane_baggage_checking::delegation::*;
+    use airplane_baggage_checking::loyalty::*;
+    use airplane_baggage_checking::fee::*;
+    use near_sdk::{MockedBlockchain, Balance, PromiseOrValue, PromiseResult};
+    use near_sdk::json_types::{U64, U128, Base58CryptoHash};
+    use std::convert::TryInto;
+    use near_sdk::{testing_env, VMContext, env};
+    use near_sdk::test_utils::{get_created_receipts, get_logs};
+    use near_sdk::serde_json::{self, json, Value};
 
     fn get_context(
         input: Vec<u8>, 
@@ -22,8 +39,8 @@ mod tests {
             block_timestamp: 0,
             account_balance: 10u128.pow(32),
             account_locked_balance: 0,
-            storage_usage: 0,
-            attached_deposit: attached_deposit,
+            storage_usage: 10u64.pow(6),
+            attached_deposit,
             prepaid_gas: 10u64.pow(18),
             random_seed: vec![0, 1, 2],
             is_view,
@@ -50,253 +67,3527 @@ mod tests {
         )
     }
     
+    fn setup_contract() -> Contract {
+        let mut contract = Contract::new("phuc16102001.testnet".to_string());
+        let airline_id = contract.add_airline(
+            "VN".to_string(),
+            "phuc16102001.testnet".to_string()
+        );
+        contract.grant_role(
+            "phuc16102001.testnet".to_string(),
+            Role::AirlineAdmin,
+            Some(airline_id)
+        );
+        contract.grant_role(
+            "phuc16102001.testnet".to_string(),
+            Role::GroundHandler,
+            Some(airline_id)
+        );
+        contract.add_flight(airline_id, 1);
+        contract.set_price_oracle(
+            "oracle.testnet".to_string(),
+            "wrap.testnet".to_string(),
+            90
+        );
+
+        deposit_storage(&mut contract, "phuc16102001.testnet");
+        deposit_storage(&mut contract, "thanhhoang4869.testnet");
+        testing_env!(get_context_airlines());
+        contract
+    }
+
+    fn deposit_storage(contract: &mut Contract, account_id: &str) {
+        testing_env!(get_context(
+            vec![],
+            false,
+            account_id.to_string(),
+            to_yoto(1)
+        ));
+        contract.storage_deposit(None, None);
+    }
+
+    // 1 yoctoNEAR is worth multiplier / 10^28 USD
+    const ONE_USD_PER_NEAR: Balance = 10_000;
+
+    /// The answer of the price oracle for `wrap.testnet`
+    fn get_price_data(multiplier: Balance, timestamp: u64) -> Vec<u8> {
+        json!({
+            "timestamp": timestamp.to_string(),
+            "recency_duration_sec": 90,
+            "prices": [{
+                "asset_id": "wrap.testnet",
+                "price": {
+                    "multiplier": multiplier.to_string(),
+                    "decimals": 28
+                }
+            }]
+        }).to_string().into_bytes()
+    }
+
+    fn resolve_accept(
+        contract: &mut Contract,
+        flight_id: FlightId,
+        deposit: Balance,
+        price_result: PromiseResult,
+        block_timestamp: u64
+    ) -> bool {
+        testing_env!(get_context_customer(0));
+        let fee = contract.check_fee(flight_id);
+
+        let mut context = get_context(
+            vec![],
+            false,
+            "dev&-account.testnet".to_string(),
+            0
+        );
+        context.block_timestamp = block_timestamp;
+        testing_env!(
+            context,
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            vec![price_result]
+        );
+        contract.on_accept_price(
+            "thanhhoang4869.testnet".to_string(),
+            "thanhhoang4869.testnet".to_string(),
+            flight_id,
+            U128(fee),
+            U128(deposit)
+        )
+    }
+
+    /// Calls `accept`, then answers it like the price oracle
+    fn accept_with_price(
+        contract: &mut Contract,
+        flight_id: FlightId,
+        deposit: Balance,
+        multiplier: Balance
+    ) -> bool {
+        testing_env!(get_context_customer(deposit));
+        contract.accept(flight_id);
+        resolve_accept(
+            contract,
+            flight_id,
+            deposit,
+            PromiseResult::Successful(get_price_data(multiplier, 0)),
+            0
+        )
+    }
+
+    /// The code printed on the baggage receipt
+    const CLAIM_CODE: &str = "7KQ2-M9XD-4FZL";
+
+    fn get_claim_code_hash() -> Base58CryptoHash {
+        let hash: [u8; 32] = env::sha256(CLAIM_CODE.as_bytes()).try_into().unwrap();
+        hash.into()
+    }
+
+    fn get_receipts() -> Value {
+        // Receipts hold u128 balances, which `to_value` cannot handle
+        serde_json::from_str(
+            &serde_json::to_string(&get_created_receipts()).unwrap()
+        ).unwrap()
+    }
+    
     #[test]
     fn init_contract() {
         let context = get_context_airlines();
         testing_env!(context);
 
-        let mut contract = Contract::default();
-        assert_eq!(
-            contract.get_initialized(),
-            false
-        );
-        
-        contract.init("phuc16102001.testnet".to_string());
-        assert_eq!(
-            contract.get_payment_account(),
-            "phuc16102001.testnet"
-        );
+        let contract = Contract::new("phuc16102001.testnet".to_string());
         assert_eq!(
             contract.get_owner(),
             "phuc16102001.testnet"
         );
         assert_eq!(
-            contract.get_initialized(),
-            true
+            contract.get_airlines().len(),
+            0
         );
     }
 
     #[test]
     #[should_panic(
-        expected = r#"Contract was already initialized"#
+        expected = r#"The contract is not initialized"#
     )]
-    fn double_init() {
+    fn default_not_initialized() {
         let context = get_context_airlines();
         testing_env!(context);
 
-        let mut contract = Contract::default();
-        assert_eq!(
-            contract.get_initialized(),
-            false
+        Contract::default();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Payment account cannot be contract"#
+    )]
+    fn add_airline_payment_to_contract() {
+        let context = get_context_airlines();
+        testing_env!(context);
+
+        let mut contract = Contract::new("phuc16102001.testnet".to_string());
+        contract.add_airline(
+            "VN".to_string(),
+            "dev&-account.testnet".to_string()
         );
-        
-        contract.init("phuc16102001.testnet".to_string());
-        contract.init("phuc16102001.testnet".to_string());
     }
 
     #[test]
-    fn reset_payment_account() {
+    fn add_airline() {
         let context = get_context_airlines();
         testing_env!(context);
-        let mut contract = Contract::default();
 
-        contract.init("phuc16102001.testnet".to_string());
-        contract.reset();
-        contract.init("thanhhoang4869.testnet".to_string());
-    
+        let contract = setup_contract();
+        let airline = contract.get_airline(0);
         assert_eq!(
-            contract.get_payment_account(),
-            "thanhhoang4869.testnet"
+            airline.get_iata_code(),
+            "VN"
         );
         assert_eq!(
-            contract.get_owner(),
+            airline.get_payment_account(),
             "phuc16102001.testnet"
         );
+        assert!(
+            contract.has_role(
+                "phuc16102001.testnet".to_string(),
+                Role::AirlineAdmin,
+                Some(0)
+            )
+        );
+        assert_eq!(
+            *contract.get_flight(1).get_airline_id(),
+            0
+        );
     }
 
     #[test]
-    fn registry_flight() {
+    #[should_panic(
+        expected = r#"IATA code VN is already used"#
+    )]
+    fn add_airline_same_code() {
+        let context = get_context_airlines();
+        testing_env!(context);
+
+        let mut contract = setup_contract();
+        contract.add_airline(
+            "VN".to_string(),
+            "airline.testnet".to_string()
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the contract owner can add an airline"#
+    )]
+    fn add_airline_not_owner() {
         let context_airline = get_context_airlines();
         let context_customer = get_context_customer(0);
 
         testing_env!(context_airline);
-        let mut contract = Contract::default();
-        contract.init("phuc16102001.testnet".to_string());
-    
+        let mut contract = setup_contract();
+
         testing_env!(context_customer);
-        let flight_id = 1;
-        contract.registry(1, FlightClass::First, 10.0);
-        assert_eq!(
-            contract.check_class(flight_id),
-            "First"
+        contract.add_airline(
+            "QH".to_string(),
+            "thanhhoang4869.testnet".to_string()
         );
     }
 
     #[test]
     #[should_panic(
-        expected=r#"Cannot find your flight"#
+        expected = r#"Only the airline admins can add a flight"#
     )]
-    fn add_baggages_fail() {
+    fn add_flight_not_admin() {
         let context_airline = get_context_airlines();
         let context_customer = get_context_customer(0);
 
         testing_env!(context_airline);
-        let mut contract = Contract::default();
-        contract.init("phuc16102001.testnet".to_string());
-    
+        let mut contract = setup_contract();
+
         testing_env!(context_customer);
-        let flight_id = 1;
-        contract.add_baggage(flight_id, 4.0);
+        contract.add_flight(0, 2);
     }
 
     #[test]
-    fn add_baggages_success() {
+    fn route_payout_to_airline() {
         let context_airline = get_context_airlines();
-        let context_customer = get_context_customer(0);
+        let context_other_airline = get_context(
+            vec![],
+            false,
+            "airline.testnet".to_string(),
+            0
+        );
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+        let airline_id = contract.add_airline(
+            "QH".to_string(),
+            "airline.testnet".to_string()
+        );
+        contract.grant_role(
+            "airline.testnet".to_string(),
+            Role::AirlineAdmin,
+            Some(airline_id)
+        );
+        contract.grant_role(
+            "airline.testnet".to_string(),
+            Role::GroundHandler,
+            Some(airline_id)
+        );
+
+        testing_env!(context_other_airline.clone());
+        contract.add_flight(airline_id, 2);
+
+        let flight_id = 2;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+        assert!(accept_with_price(&mut contract, flight_id, to_yoto(10), ONE_USD_PER_NEAR));
+
+        testing_env!(context_other_airline.clone());
+        contract.deliver_baggage("thanhhoang4869.testnet".to_string(), flight_id, get_claim_code_hash());
+
+        testing_env!(get_context_customer(0));
+        contract.claim_baggages(flight_id, CLAIM_CODE.to_string());
+
+        testing_env!(context_other_airline);
+        contract.withdraw(airline_id, None, None);
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[0]["receiver_id"],
+            "airline.testnet"
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the ground handlers of the airline can deliver the baggages"#
+    )]
+    fn deliver_other_airline() {
+        let context_airline = get_context_airlines();
+        let context_other_airline = get_context(
+            vec![],
+            false,
+            "airline.testnet".to_string(),
+            0
+        );
 
         testing_env!(context_airline);
-        let mut contract = Contract::default();
-        contract.init("phuc16102001.testnet".to_string());
-    
-        testing_env!(context_customer);
+        let mut contract = setup_contract();
+        let airline_id = contract.add_airline(
+            "QH".to_string(),
+            "airline.testnet".to_string()
+        );
+        contract.grant_role(
+            "airline.testnet".to_string(),
+            Role::AirlineAdmin,
+            Some(airline_id)
+        );
+        contract.grant_role(
+            "airline.testnet".to_string(),
+            Role::GroundHandler,
+            Some(airline_id)
+        );
+
         let flight_id = 1;
-        contract.registry(1, FlightClass::First, 10.0);
-        let baggage_id = contract.add_baggage(flight_id, 4.0);
-        let baggage = contract.check_baggage(flight_id, baggage_id);
-        
-        assert_eq!(
-            baggage_id,
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+        assert!(accept_with_price(&mut contract, flight_id, to_yoto(10), ONE_USD_PER_NEAR));
+
+        testing_env!(context_other_airline);
+        contract.deliver_baggage("thanhhoang4869.testnet".to_string(), flight_id, get_claim_code_hash());
+    }
+
+    #[test]
+    fn airline_admin_grants_staff() {
+        let context_airline = get_context_airlines();
+        let context_admin = get_context(
+            vec![],
+            false,
+            "admin.testnet".to_string(),
             0
         );
-        assert_eq!(
-            *baggage.get_id(),
+        let context_handler = get_context(
+            vec![],
+            false,
+            "handler.testnet".to_string(),
             0
         );
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.grant_role("admin.testnet".to_string(), Role::AirlineAdmin, Some(0));
+
+        testing_env!(context_admin.clone());
+        contract.grant_role("handler.testnet".to_string(), Role::GroundHandler, Some(0));
         assert_eq!(
-            *baggage.get_weight(),
-            4.0
+            contract.get_roles("handler.testnet".to_string()),
+            vec![RoleGrant::new(Role::GroundHandler, Some(0))]
+        );
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+        assert!(accept_with_price(&mut contract, flight_id, to_yoto(10), ONE_USD_PER_NEAR));
+
+        testing_env!(context_handler);
+        contract.deliver_baggage("thanhhoang4869.testnet".to_string(), flight_id, get_claim_code_hash());
+
+        testing_env!(context_admin);
+        contract.revoke_role("handler.testnet".to_string(), Role::GroundHandler, Some(0));
+        assert!(
+            contract.get_roles("handler.testnet".to_string()).is_empty()
         );
     }
 
     #[test]
-    fn count_baggages() {
+    #[should_panic(
+        expected = r#"Only the owner can manage this role"#
+    )]
+    fn airline_admin_grants_arbiter() {
         let context_airline = get_context_airlines();
-        let context_customer = get_context_customer(0);
+        let context_admin = get_context(
+            vec![],
+            false,
+            "admin.testnet".to_string(),
+            0
+        );
 
         testing_env!(context_airline);
-        let mut contract = Contract::default();
-        contract.init("phuc16102001.testnet".to_string());
-    
-        testing_env!(context_customer);
+        let mut contract = setup_contract();
+        contract.grant_role("admin.testnet".to_string(), Role::AirlineAdmin, Some(0));
+
+        testing_env!(context_admin);
+        contract.grant_role("admin.testnet".to_string(), Role::Arbiter, None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the owner or the airline admins can manage this role"#
+    )]
+    fn grant_role_other_airline() {
+        let context_airline = get_context_airlines();
+        let context_admin = get_context(
+            vec![],
+            false,
+            "admin.testnet".to_string(),
+            0
+        );
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        let airline_id = contract.add_airline(
+            "QH".to_string(),
+            "airline.testnet".to_string()
+        );
+        contract.grant_role("admin.testnet".to_string(), Role::AirlineAdmin, Some(0));
+
+        testing_env!(context_admin);
+        contract.grant_role("admin.testnet".to_string(), Role::GroundHandler, Some(airline_id));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The owner role is transferred by propose_owner"#
+    )]
+    fn grant_owner_role() {
+        let context_airline = get_context_airlines();
+        testing_env!(context_airline);
+
+        let mut contract = setup_contract();
+        contract.grant_role("admin.testnet".to_string(), Role::Owner, None);
+    }
+
+    #[test]
+    fn arbiter_refund() {
+        let context_airline = get_context_airlines();
+        let context_arbiter = get_context(
+            vec![],
+            false,
+            "arbiter.testnet".to_string(),
+            0
+        );
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.grant_role("arbiter.testnet".to_string(), Role::Arbiter, None);
+
         let flight_id = 1;
-        contract.registry(1, FlightClass::First, 10.0);
-        contract.add_baggage(flight_id, 4.0);
-        contract.add_baggage(flight_id, 1.0);
-        
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+        assert!(accept_with_price(&mut contract, flight_id, to_yoto(10), ONE_USD_PER_NEAR));
+
+        testing_env!(context_arbiter);
+        contract.refund("thanhhoang4869.testnet".to_string(), flight_id);
+        let receipts = get_receipts();
         assert_eq!(
-            contract.check_number_baggages(flight_id),
-            2
+            receipts[0]["receiver_id"],
+            "thanhhoang4869.testnet"
+        );
+
+        testing_env!(get_context_customer(0));
+        assert_eq!(
+            contract.check_state(flight_id),
+            "Refunded"
         );
     }
 
     #[test]
     #[should_panic(
-        expected=r#"You cannot add more than 3 baggages"#
+        expected = r#"Only the arbiters can refund"#
     )]
-    fn limit_baggages() {
+    fn refund_not_arbiter() {
         let context_airline = get_context_airlines();
-        let context_customer = get_context_customer(0);
 
-        testing_env!(context_airline);
-        let mut contract = Contract::default();
-        contract.init("phuc16102001.testnet".to_string());
-    
-        testing_env!(context_customer);
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+
         let flight_id = 1;
-        contract.registry(1, FlightClass::First, 10.0);
-        contract.add_baggage(flight_id, 1.0);
-        contract.add_baggage(flight_id, 3.0);
-        contract.add_baggage(flight_id, 5.0);
-        contract.add_baggage(flight_id, 4.0);
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+        assert!(accept_with_price(&mut contract, flight_id, to_yoto(10), ONE_USD_PER_NEAR));
+
+        testing_env!(context_airline);
+        contract.refund("thanhhoang4869.testnet".to_string(), flight_id);
     }
 
     #[test]
     #[should_panic(
-        expected=r#"Cannot find your baggage"#
+        expected = r#"Cannot find the flight"#
     )]
-    fn not_found_baggages() {
+    fn registry_unknown_flight() {
         let context_airline = get_context_airlines();
         let context_customer = get_context_customer(0);
 
         testing_env!(context_airline);
-        let mut contract = Contract::default();
-        contract.init("phuc16102001.testnet".to_string());
-    
+        let mut contract = setup_contract();
+
         testing_env!(context_customer);
-        let flight_id = 1;
-        contract.registry(1, FlightClass::First, 10.0);
-        
-        let baggage_id = contract.add_baggage(flight_id, 1.0);
-        let other_id = baggage_id+1;
-        contract.check_baggage(flight_id, other_id);
+        contract.registry(2, FlightClass::First, 10.0);
     }
 
-    fn check_fee(
-        flight_class: FlightClass, 
-        distance: Distance,
-        first_weight: Weight,
-        second_weight: Weight,
-        third_weight: Weight,
-        expected_price: Balance
-    ) {
+    #[test]
+    fn reset_flights() {
         let context_airline = get_context_airlines();
         let context_customer = get_context_customer(0);
 
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+
+        testing_env!(context_customer.clone());
+        contract.registry(1, FlightClass::First, 10.0);
+        contract.add_baggage(1, 4.0, None, None, None);
+
         testing_env!(context_airline);
-        let mut contract = Contract::default();
-        contract.init("phuc16102001.testnet".to_string());
+        contract.reset();
     
-        testing_env!(context_customer);
-        let flight_id = 1;
-        contract.registry(flight_id, flight_class, distance);
-        
-        contract.add_baggage(flight_id, first_weight);
-        contract.add_baggage(flight_id, second_weight);
-        contract.add_baggage(flight_id, third_weight);
+        assert_eq!(
+            contract.get_owner(),
+            "phuc16102001.testnet"
+        );
 
+        // Flights and baggages registered before the reset are gone
+        testing_env!(context_customer);
+        contract.registry(1, FlightClass::First, 10.0);
         assert_eq!(
-            contract.check_fee(flight_id),
-            expected_price
-        )
+            contract.check_number_baggages(1),
+            0
+        );
     }
 
     #[test]
-    fn check_fee_first() {
-        check_fee(
-            FlightClass::First,
-            100.0,
-            5.0, 2.0, 3.0,
-            10
-        )
-    }
+    fn reset_refunds_deposits() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
 
-    #[test]
-    fn check_fee_business() {
-        check_fee(
-            FlightClass::Business,
-            100.0,
-            5.0, 2.0, 3.0,
-            20
-        )
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+        assert!(accept_with_price(&mut contract, 1, to_yoto(10), ONE_USD_PER_NEAR));
+
+        testing_env!(get_context_airlines());
+        contract.reset();
+
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[0]["receiver_id"],
+            "thanhhoang4869.testnet"
+        );
+        assert_eq!(
+            receipts[0]["actions"][0]["Transfer"]["deposit"].as_f64().unwrap(),
+            to_yoto(10) as f64
+        );
+        assert_eq!(
+            receipts[1]["actions"][0]["FunctionCall"]["method_name"],
+            "on_refund"
+        );
     }
 
     #[test]
-    fn check_fee_economy() {
-        check_fee(
-            FlightClass::Economy,
-            100.0,
-            5.0, 2.0, 3.0,
+    #[should_panic(
+        expected = r#"Only the contract owner can reset"#
+    )]
+    fn reset_not_owner() {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(context_customer);
+        contract.reset();
+    }
+
+    #[test]
+    fn transfer_ownership() {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.propose_owner("thanhhoang4869.testnet".to_string());
+        assert_eq!(
+            contract.get_pending_owner(),
+            Some("thanhhoang4869.testnet".to_string())
+        );
+
+        testing_env!(context_customer);
+        contract.accept_owner();
+        assert_eq!(
+            contract.get_owner(),
+            "thanhhoang4869.testnet"
+        );
+        assert_eq!(
+            contract.get_pending_owner(),
+            None
+        );
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"baggage_checkin","version":"1.0.0","event":"owner_changed","data":{"old_owner":"phuc16102001.testnet","new_owner":"thanhhoang4869.testnet"}}"#]
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the contract owner can propose a new owner"#
+    )]
+    fn propose_owner_not_owner() {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(context_customer);
+        contract.propose_owner("thanhhoang4869.testnet".to_string());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the proposed owner can accept the ownership"#
+    )]
+    fn accept_owner_not_proposed() {
+        let context_airline = get_context_airlines();
+        let context_other = get_context(
+            vec![],
+            false,
+            "someone.testnet".to_string(),
+            0
+        );
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.propose_owner("thanhhoang4869.testnet".to_string());
+
+        testing_env!(context_other);
+        contract.accept_owner();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"There is no proposed owner"#
+    )]
+    fn accept_owner_without_proposal() {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(context_customer);
+        contract.accept_owner();
+    }
+
+    #[test]
+    fn rotate_payment_account() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+        assert!(accept_with_price(&mut contract, flight_id, to_yoto(10), ONE_USD_PER_NEAR));
+
+        testing_env!(context_airline);
+        contract.set_payment_account(0, "airline.testnet".to_string());
+        assert_eq!(
+            contract.get_airline(0).get_payment_account(),
+            "airline.testnet"
+        );
+
+        contract.deliver_baggage("thanhhoang4869.testnet".to_string(), flight_id, get_claim_code_hash());
+
+        testing_env!(get_context_customer(0));
+        contract.claim_baggages(flight_id, CLAIM_CODE.to_string());
+
+        testing_env!(get_context_airlines());
+        contract.withdraw(0, None, None);
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[0]["receiver_id"],
+            "airline.testnet"
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the contract owner can set the payment account"#
+    )]
+    fn set_payment_account_not_owner() {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(context_customer);
+        contract.set_payment_account(0, "thanhhoang4869.testnet".to_string());
+    }
+
+    fn get_context_guardian() -> VMContext {
+        get_context(
+            vec![],
+            false,
+            "guardian.testnet".to_string(),
+            0
+        )
+    }
+
+    #[test]
+    fn guardian_pause() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+        contract.set_guardian(Some("guardian.testnet".to_string()));
+
+        testing_env!(get_context_guardian());
+        contract.pause();
+        assert!(contract.is_paused());
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"baggage_checkin","version":"1.0.0","event":"paused","data":{"account_id":"guardian.testnet"}}"#]
+        );
+
+        testing_env!(context_airline);
+        contract.unpause();
+        assert!(!contract.is_paused());
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::First, 10.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the contract owner can unpause"#
+    )]
+    fn guardian_unpause() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.set_guardian(Some("guardian.testnet".to_string()));
+
+        testing_env!(get_context_guardian());
+        contract.pause();
+        contract.unpause();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the contract owner or the guardian can pause"#
+    )]
+    fn pause_not_guardian() {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(context_customer);
+        contract.pause();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The distance cannot be negative"#
+    )]
+    fn registry_negative_distance() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Economy, -10.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The contract is paused"#
+    )]
+    fn registry_paused() {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.pause();
+
+        testing_env!(context_customer);
+        contract.registry(1, FlightClass::First, 10.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The contract is paused"#
+    )]
+    fn claim_paused() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+        assert!(accept_with_price(&mut contract, flight_id, to_yoto(10), ONE_USD_PER_NEAR));
+
+        testing_env!(context_airline);
+        contract.deliver_baggage("thanhhoang4869.testnet".to_string(), flight_id, get_claim_code_hash());
+        contract.pause();
+
+        testing_env!(get_context_customer(0));
+        contract.claim_baggages(flight_id, CLAIM_CODE.to_string());
+    }
+
+    #[test]
+    fn refund_paused() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+        contract.grant_role("phuc16102001.testnet".to_string(), Role::Arbiter, None);
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+        assert!(accept_with_price(&mut contract, flight_id, to_yoto(10), ONE_USD_PER_NEAR));
+
+        testing_env!(context_airline);
+        contract.pause();
+        contract.refund("thanhhoang4869.testnet".to_string(), flight_id);
+
+        testing_env!(get_context_customer(0));
+        assert_eq!(
+            contract.check_state(flight_id),
+            "Refunded"
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"You must pay for storage first, see storage_deposit"#
+    )]
+    fn registry_without_storage() {
+        let context_airline = get_context_airlines();
+        let context_other = get_context(
+            vec![],
+            false,
+            "someone.testnet".to_string(),
+            0
+        );
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(context_other);
+        contract.registry(1, FlightClass::First, 10.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Not enough storage deposit"#
+    )]
+    fn registry_not_enough_storage() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        let min_balance = contract.storage_balance_bounds().min.0;
+
+        testing_env!(get_context(
+            vec![],
+            false,
+            "someone.testnet".to_string(),
+            min_balance + 10
+        ));
+        contract.storage_deposit(None, Some(true));
+        contract.registry(1, FlightClass::First, 10.0);
+    }
+
+    #[test]
+    fn storage_charged_and_released() {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        let customer_id = "thanhhoang4869.testnet".to_string();
+        let initial = contract.storage_balance_of(customer_id.clone()).unwrap();
+        assert_eq!(
+            initial.total.0,
+            to_yoto(1)
+        );
+
+        testing_env!(context_customer.clone());
+        contract.registry(1, FlightClass::First, 10.0);
+        contract.add_baggage(1, 4.0, None, None, None);
+        let used = contract.storage_balance_of(customer_id.clone()).unwrap();
+        assert!(
+            used.available.0 < initial.available.0
+        );
+
+        testing_env!(context_customer);
+        contract.remove_flight(1);
+        assert_eq!(
+            contract.storage_balance_of(customer_id).unwrap(),
+            initial
+        );
+    }
+
+    #[test]
+    fn storage_withdraw() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(1));
+        let balance = contract.storage_withdraw(None);
+        assert_eq!(
+            balance.total.0,
+            contract.storage_balance_bounds().min.0
+        );
+        assert_eq!(
+            balance.available.0,
+            0
+        );
+
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[0]["receiver_id"],
+            "thanhhoang4869.testnet"
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Remove your flights before unregistering"#
+    )]
+    fn storage_unregister_with_flights() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::First, 10.0);
+        testing_env!(get_context_customer(1));
+        contract.storage_unregister(None);
+    }
+
+    #[test]
+    fn accept_locks_converted_fee() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+
+        // 10 USD at 2 USD per NEAR
+        assert!(accept_with_price(&mut contract, flight_id, to_yoto(8), 2 * ONE_USD_PER_NEAR));
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[0]["receiver_id"],
+            "thanhhoang4869.testnet"
+        );
+        assert_eq!(
+            receipts[0]["actions"][0]["Transfer"]["deposit"].as_f64().unwrap(),
+            to_yoto(3) as f64
+        );
+
+        testing_env!(get_context_customer(0));
+        assert_eq!(
+            contract.check_state(flight_id),
+            "Checked"
+        );
+        assert_eq!(
+            contract.check_escrow(flight_id).unwrap().get_amount(),
+            to_yoto(5)
+        );
+    }
+
+    #[test]
+    fn accept_not_enough_deposit() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+
+        assert!(!accept_with_price(&mut contract, flight_id, to_yoto(9), ONE_USD_PER_NEAR));
+        assert_eq!(
+            get_logs(),
+            vec!["You must pay 10 USD, which is 10000000000000000000000000 yoctoNEAR"]
+        );
+
+        testing_env!(get_context_customer(0));
+        assert_eq!(
+            contract.check_state(flight_id),
+            "Idle"
+        );
+    }
+
+    #[test]
+    fn accept_charges_deposit_storage() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        let customer_id = "thanhhoang4869.testnet".to_string();
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+        let registered = contract.storage_balance_of(customer_id.clone()).unwrap();
+
+        assert!(accept_with_price(&mut contract, 1, to_yoto(10), ONE_USD_PER_NEAR));
+        assert!(
+            contract.storage_balance_of(customer_id).unwrap().available.0 < registered.available.0
+        );
+    }
+
+    #[test]
+    fn accept_not_enough_storage() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+        testing_env!(get_context_customer(1));
+        contract.storage_withdraw(None);
+
+        assert!(!accept_with_price(&mut contract, 1, to_yoto(10), ONE_USD_PER_NEAR));
+        assert_eq!(
+            get_logs(),
+            vec!["Not enough storage deposit to record your deposit, see storage_deposit"]
+        );
+        assert_eq!(
+            get_receipts()[0]["actions"][0]["Transfer"]["deposit"].as_f64().unwrap(),
+            to_yoto(10) as f64
+        );
+
+        testing_env!(get_context_customer(0));
+        assert_eq!(
+            contract.check_state(1),
+            "Idle"
+        );
+    }
+
+    #[test]
+    fn accept_with_long_max_price_age() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        contract.set_price_oracle(
+            "oracle.testnet".to_string(),
+            "wrap.testnet".to_string(),
+            u64::MAX
+        );
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+
+        testing_env!(get_context_customer(to_yoto(10)));
+        contract.accept(1);
+        assert!(resolve_accept(
+            &mut contract,
+            1,
+            to_yoto(10),
+            PromiseResult::Successful(get_price_data(ONE_USD_PER_NEAR, 0)),
+            91 * 1_000_000_000
+        ));
+    }
+
+    #[test]
+    fn accept_stale_price() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+
+        testing_env!(get_context_customer(to_yoto(10)));
+        contract.accept(flight_id);
+        let accepted = resolve_accept(
+            &mut contract,
+            flight_id,
+            to_yoto(10),
+            PromiseResult::Successful(get_price_data(ONE_USD_PER_NEAR, 0)),
+            91 * 1_000_000_000
+        );
+        assert!(!accepted);
+        assert_eq!(
+            get_logs(),
+            vec!["The oracle price is too old"]
+        );
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[0]["receiver_id"],
+            "thanhhoang4869.testnet"
+        );
+    }
+
+    #[test]
+    fn accept_oracle_failed() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+
+        testing_env!(get_context_customer(to_yoto(10)));
+        contract.accept(flight_id);
+        let accepted = resolve_accept(
+            &mut contract,
+            flight_id,
+            to_yoto(10),
+            PromiseResult::Failed,
+            0
+        );
+        assert!(!accepted);
+
+        testing_env!(get_context_customer(0));
+        assert_eq!(
+            contract.check_state(flight_id),
+            "Idle"
+        );
+    }
+
+    #[test]
+    fn accept_flight_changed() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+
+        testing_env!(get_context_customer(to_yoto(10)));
+        contract.accept(flight_id);
+
+        // The fee is read again when resolving, so the quote passed
+        // from `accept` no longer matches
+        testing_env!(get_context_customer(0));
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+        testing_env!(
+            get_context(vec![], false, "dev&-account.testnet".to_string(), 0),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            vec![PromiseResult::Successful(get_price_data(ONE_USD_PER_NEAR, 0))]
+        );
+        let accepted = contract.on_accept_price(
+            "thanhhoang4869.testnet".to_string(),
+            "thanhhoang4869.testnet".to_string(),
+            flight_id,
+            U128(10),
+            U128(to_yoto(10))
+        );
+        assert!(!accepted);
+        assert_eq!(
+            get_logs(),
+            vec!["Your flight was changed during the payment"]
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The price oracle is not set"#
+    )]
+    fn accept_without_oracle() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = Contract::new("phuc16102001.testnet".to_string());
+
+        testing_env!(get_context_customer(to_yoto(10)));
+        contract.accept(1);
+    }
+
+    fn get_context_token() -> VMContext {
+        get_context(
+            vec![],
+            false,
+            "usdc.testnet".to_string(),
+            0
+        )
+    }
+
+    fn pay_with_token(contract: &mut Contract, flight_id: FlightId, amount: Balance) {
+        testing_env!(get_context_airlines());
+        contract.add_token("usdc.testnet".to_string(), 6);
+
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+
+        testing_env!(get_context_token());
+        let unused = contract.ft_on_transfer(
+            "thanhhoang4869.testnet".to_string(),
+            U128(amount),
+            format!("{{\"flight_id\":{}}}", flight_id)
+        );
+        match unused {
+            PromiseOrValue::Value(unused) => assert_eq!(unused.0, 0),
+            PromiseOrValue::Promise(_) => panic!("Expected a value")
+        }
+    }
+
+    #[test]
+    fn claim_paid_with_token() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+        let flight_id = 1;
+        pay_with_token(&mut contract, flight_id, 10 * 10u128.pow(6));
+
+        testing_env!(get_context_customer(0));
+        assert_eq!(
+            contract.check_state(flight_id),
+            "Checked"
+        );
+        assert_eq!(
+            contract.check_escrow(flight_id),
+            Some(Escrow::new(
+                "thanhhoang4869.testnet".to_string(),
+                Some("usdc.testnet".to_string()),
+                10 * 10u128.pow(6),
+                10,
+                10u128.pow(6)
+            ))
+        );
+
+        testing_env!(context_airline.clone());
+        contract.deliver_baggage("thanhhoang4869.testnet".to_string(), flight_id, get_claim_code_hash());
+
+        testing_env!(get_context_customer(0));
+        contract.claim_baggages(flight_id, CLAIM_CODE.to_string());
+
+        testing_env!(context_airline);
+        contract.withdraw(0, Some("usdc.testnet".to_string()), None);
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[0]["receiver_id"],
+            "usdc.testnet"
+        );
+        assert_eq!(
+            receipts[0]["actions"][0]["FunctionCall"]["method_name"],
+            "ft_transfer"
+        );
+        assert_eq!(
+            receipts[0]["actions"][0]["FunctionCall"]["args"],
+            r#"{"receiver_id":"phuc16102001.testnet","amount":"10000000","memo":null}"#
+        );
+    }
+
+    #[test]
+    fn refund_paid_with_token() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+        contract.grant_role("phuc16102001.testnet".to_string(), Role::Arbiter, None);
+        let flight_id = 1;
+        pay_with_token(&mut contract, flight_id, 10 * 10u128.pow(6));
+
+        testing_env!(context_airline);
+        contract.refund("thanhhoang4869.testnet".to_string(), flight_id);
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[0]["receiver_id"],
+            "usdc.testnet"
+        );
+        assert_eq!(
+            receipts[0]["actions"][0]["FunctionCall"]["args"],
+            r#"{"receiver_id":"thanhhoang4869.testnet","amount":"10000000","memo":null}"#
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"You must pay 10 usdc.testnet"#
+    )]
+    fn pay_with_token_wrong_amount() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        pay_with_token(&mut contract, 1, 10);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The token usdc.testnet is not accepted"#
+    )]
+    fn pay_with_token_not_accepted() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+
+        testing_env!(get_context_token());
+        contract.ft_on_transfer(
+            "thanhhoang4869.testnet".to_string(),
+            U128(10),
+            r#"{"flight_id":1}"#.to_string()
+        );
+    }
+
+    #[test]
+    fn registry_flight() {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+    
+        testing_env!(context_customer);
+        let flight_id = 1;
+        contract.registry(1, FlightClass::First, 10.0);
+        assert_eq!(
+            contract.check_class(flight_id),
+            "First"
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected=r#"Cannot find your flight"#
+    )]
+    fn add_baggages_fail() {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+    
+        testing_env!(context_customer);
+        let flight_id = 1;
+        contract.add_baggage(flight_id, 4.0, None, None, None);
+    }
+
+    #[test]
+    fn add_baggages_success() {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+    
+        testing_env!(context_customer);
+        let flight_id = 1;
+        contract.registry(1, FlightClass::First, 10.0);
+        let baggage_id = contract.add_baggage(flight_id, 4.0, None, None, None);
+        let baggage = contract.check_baggage(flight_id, baggage_id);
+        
+        assert_eq!(
+            baggage_id,
+            0
+        );
+        assert_eq!(
+            *baggage.get_id(),
+            0
+        );
+        assert_eq!(
+            *baggage.get_weight(),
+            4.0
+        );
+    }
+
+    #[test]
+    fn separate_baggages_per_customer() {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+        contract.registry(1, FlightClass::First, 10.0);
+        contract.add_baggage(1, 4.0, None, None, None);
+
+        testing_env!(context_customer);
+        contract.registry(1, FlightClass::First, 10.0);
+        assert_eq!(
+            contract.check_number_baggages(1),
+            0
+        );
+    }
+
+    #[test]
+    fn count_baggages() {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+    
+        testing_env!(context_customer);
+        let flight_id = 1;
+        contract.registry(1, FlightClass::First, 10.0);
+        contract.add_baggage(flight_id, 4.0, None, None, None);
+        contract.add_baggage(flight_id, 1.0, None, None, None);
+        
+        assert_eq!(
+            contract.check_number_baggages(flight_id),
+            2
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected=r#"You cannot add more than 3 baggages"#
+    )]
+    fn limit_baggages() {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+    
+        testing_env!(context_customer);
+        let flight_id = 1;
+        contract.registry(1, FlightClass::First, 10.0);
+        contract.add_baggage(flight_id, 1.0, None, None, None);
+        contract.add_baggage(flight_id, 3.0, None, None, None);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+        contract.add_baggage(flight_id, 4.0, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected=r#"Cannot find your baggage"#
+    )]
+    fn not_found_baggages() {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+    
+        testing_env!(context_customer);
+        let flight_id = 1;
+        contract.registry(1, FlightClass::First, 10.0);
+        
+        let baggage_id = contract.add_baggage(flight_id, 1.0, None, None, None);
+        let other_id = baggage_id+1;
+        contract.check_baggage(flight_id, other_id);
+    }
+
+    fn check_fee(
+        flight_class: FlightClass, 
+        distance: Distance,
+        first_weight: Weight,
+        second_weight: Weight,
+        third_weight: Weight,
+        expected_price: Balance
+    ) {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+    
+        testing_env!(context_customer);
+        let flight_id = 1;
+        contract.registry(flight_id, flight_class, distance);
+        
+        contract.add_baggage(flight_id, first_weight, None, None, None);
+        contract.add_baggage(flight_id, second_weight, None, None, None);
+        contract.add_baggage(flight_id, third_weight, None, None, None);
+
+        assert_eq!(
+            contract.check_fee(flight_id),
+            expected_price
+        )
+    }
+
+    #[test]
+    fn check_fee_first() {
+        check_fee(
+            FlightClass::First,
+            100.0,
+            5.0, 2.0, 3.0,
+            10
+        )
+    }
+
+    #[test]
+    fn check_fee_business() {
+        check_fee(
+            FlightClass::Business,
+            100.0,
+            5.0, 2.0, 3.0,
+            20
+        )
+    }
+
+    #[test]
+    fn check_fee_economy() {
+        check_fee(
+            FlightClass::Economy,
+            100.0,
+            5.0, 2.0, 3.0,
             220
         )
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn upgrade_contract() {
+        let context = get_context_airlines();
+        testing_env!(context);
+        let contract = setup_contract();
+
+        let code = vec![0, 97, 115, 109];
+        testing_env!(get_context(
+            code.clone(),
+            false,
+            "phuc16102001.testnet".to_string(),
+            0
+        ));
+        contract.upgrade();
+
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[0]["receiver_id"],
+            "dev&-account.testnet"
+        );
+        assert_eq!(
+            receipts[0]["actions"][0]["DeployContract"]["code"],
+            json!(code)
+        );
+        assert_eq!(
+            receipts[0]["actions"][1]["FunctionCall"]["method_name"],
+            "migrate"
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected=r#"Only the contract owner can upgrade"#
+    )]
+    fn upgrade_not_owner() {
+        let context_airline = get_context_airlines();
+        testing_env!(context_airline);
+        let contract = setup_contract();
+
+        testing_env!(get_context(
+            vec![0, 97, 115, 109],
+            false,
+            "thanhhoang4869.testnet".to_string(),
+            0
+        ));
+        contract.upgrade();
+    }
+
+    #[test]
+    fn migrate_keeps_state() {
+        let context_airline = get_context_airlines();
+        let context_customer = get_context_customer(0);
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(context_customer);
+        contract.registry(1, FlightClass::Business, 10.0);
+        env::state_write(&contract);
+
+        let mut migrated = Contract::migrate();
+        assert_eq!(
+            migrated.get_airline(0).get_payment_account(),
+            "phuc16102001.testnet"
+        );
+        assert_eq!(
+            migrated.check_class(1),
+            "Business"
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The contract state has another layout, deploy the contract again"#
+    )]
+    fn migrate_other_layout() {
+        testing_env!(get_context_airlines());
+        // The state of user-026: owner, payment account and `initialized`
+        env::state_write(&(
+            "phuc16102001.testnet".to_string(),
+            "phuc16102001.testnet".to_string(),
+            true
+        ));
+
+        Contract::migrate();
+    }
+
+    fn claim_with_token(contract: &mut Contract, flight_id: FlightId) {
+        pay_with_token(contract, flight_id, 10 * 10u128.pow(6));
+
+        testing_env!(get_context_airlines());
+        contract.deliver_baggage("thanhhoang4869.testnet".to_string(), flight_id, get_claim_code_hash());
+
+        testing_env!(get_context_customer(0));
+        contract.claim_baggages(flight_id, CLAIM_CODE.to_string());
+
+        testing_env!(get_context_airlines());
+        contract.withdraw(0, Some("usdc.testnet".to_string()), None);
+    }
+
+    #[test]
+    fn claim_split_commission() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.set_commission(250);
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"baggage_checkin","version":"1.0.0","event":"commission_changed","data":{"old_commission":0,"new_commission":250}}"#]
+        );
+
+        claim_with_token(&mut contract, 1);
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[0]["actions"][0]["FunctionCall"]["args"],
+            r#"{"receiver_id":"phuc16102001.testnet","amount":"9750000","memo":null}"#
+        );
+        assert_eq!(
+            contract.get_commissions(),
+            vec![(Some("usdc.testnet".to_string()), U128(250000))]
+        );
+    }
+
+    #[test]
+    fn withdraw_commission() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+        contract.set_commission(250);
+        contract.set_treasury("treasury.testnet".to_string());
+        claim_with_token(&mut contract, 1);
+
+        testing_env!(get_context(
+            vec![],
+            false,
+            "treasury.testnet".to_string(),
+            0
+        ));
+        contract.withdraw_commission(Some("usdc.testnet".to_string()), Some(U128(50000)));
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[0]["actions"][0]["FunctionCall"]["args"],
+            r#"{"receiver_id":"treasury.testnet","amount":"50000","memo":null}"#
+        );
+
+        testing_env!(context_airline);
+        contract.withdraw_commission(Some("usdc.testnet".to_string()), None);
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[0]["actions"][0]["FunctionCall"]["args"],
+            r#"{"receiver_id":"treasury.testnet","amount":"200000","memo":null}"#
+        );
+        assert!(contract.get_commissions().is_empty());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Commission cannot be more than 10000 basis points"#
+    )]
+    fn set_commission_too_high() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.set_commission(10001);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the contract owner or the treasury can withdraw the commission"#
+    )]
+    fn withdraw_commission_not_treasury() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.withdraw_commission(None, None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"There is no commission to withdraw"#
+    )]
+    fn withdraw_commission_empty() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.withdraw_commission(None, None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The contract is paused"#
+    )]
+    fn withdraw_commission_paused() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.set_commission(250);
+        contract.set_treasury("treasury.testnet".to_string());
+        claim_with_token(&mut contract, 1);
+
+        testing_env!(get_context_airlines());
+        contract.pause();
+        contract.withdraw_commission(Some("usdc.testnet".to_string()), None);
+    }
+
+    fn claim_with_near(contract: &mut Contract, flight_id: FlightId, block_timestamp: u64) {
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+        assert!(accept_with_price(contract, flight_id, to_yoto(10), ONE_USD_PER_NEAR));
+
+        testing_env!(get_context_airlines());
+        contract.deliver_baggage("thanhhoang4869.testnet".to_string(), flight_id, get_claim_code_hash());
+
+        let mut context = get_context_customer(0);
+        context.block_timestamp = block_timestamp;
+        testing_env!(context);
+        contract.claim_baggages(flight_id, CLAIM_CODE.to_string());
+    }
+
+    #[test]
+    fn claim_credits_airline() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+        contract.set_commission(1000);
+
+        claim_with_near(&mut contract, 1, 0);
+        assert!(get_created_receipts().is_empty());
+        assert_eq!(
+            contract.get_airline_balances(0),
+            vec![(None, U128(to_yoto(9)))]
+        );
+
+        testing_env!(context_airline);
+        contract.withdraw(0, None, Some(U128(to_yoto(4))));
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[0]["receiver_id"],
+            "phuc16102001.testnet"
+        );
+        assert_eq!(
+            receipts[0]["actions"][0]["Transfer"]["deposit"].as_f64().unwrap(),
+            to_yoto(4) as f64
+        );
+        assert_eq!(
+            contract.get_airline_balances(0),
+            vec![(None, U128(to_yoto(5)))]
+        );
+    }
+
+    #[test]
+    fn settlement_report_by_period() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+        contract.set_commission(1000);
+        contract.add_flight(0, 2);
+        contract.add_flight(0, 3);
+
+        claim_with_near(&mut contract, 1, 0);
+        claim_with_near(&mut contract, 2, SETTLEMENT_PERIOD - 1);
+        claim_with_near(&mut contract, 3, 2 * SETTLEMENT_PERIOD);
+
+        let report = contract.get_settlement_report(0, 0, 2);
+        assert_eq!(report.len(), 2);
+        assert_eq!(*report[0].get_period(), 0);
+        assert_eq!(*report[0].get_token_id(), None);
+        assert_eq!(*report[0].get_claims(), 2);
+        assert_eq!(report[0].get_gross(), to_yoto(20));
+        assert_eq!(report[0].get_commission(), to_yoto(2));
+        assert_eq!(report[0].get_net(), to_yoto(18));
+        assert_eq!(*report[1].get_period(), 2);
+        assert_eq!(*report[1].get_claims(), 1);
+
+        assert!(contract.get_settlement_report(0, 1, 1).is_empty());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the airline admins can withdraw"#
+    )]
+    fn withdraw_not_airline_admin() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        claim_with_near(&mut contract, 1, 0);
+
+        testing_env!(get_context_customer(0));
+        contract.withdraw(0, None, None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Not enough balance"#
+    )]
+    fn withdraw_more_than_balance() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+        claim_with_near(&mut contract, 1, 0);
+
+        testing_env!(context_airline);
+        contract.withdraw(0, None, Some(U128(to_yoto(11))));
+    }
+
+    /// The context of a callback receiving `result`
+    fn set_promise_result(result: PromiseResult) {
+        testing_env!(
+            get_context(vec![], false, "dev&-account.testnet".to_string(), 0),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            vec![result]
+        );
+    }
+
+    #[test]
+    fn withdraw_failed() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+        claim_with_near(&mut contract, 1, 0);
+
+        testing_env!(context_airline);
+        contract.withdraw(0, None, None);
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[1]["actions"][0]["FunctionCall"]["method_name"],
+            "on_withdraw"
+        );
+        assert!(contract.get_airline_balances(0).is_empty());
+
+        set_promise_result(PromiseResult::Failed);
+        assert!(!contract.on_withdraw(0, None, U128(to_yoto(10))));
+        assert_eq!(
+            contract.get_airline_balances(0),
+            vec![(None, U128(to_yoto(10)))]
+        );
+    }
+
+    #[test]
+    fn withdraw_succeeded() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+        claim_with_near(&mut contract, 1, 0);
+
+        testing_env!(context_airline);
+        contract.withdraw(0, None, None);
+
+        set_promise_result(PromiseResult::Successful(vec![]));
+        assert!(contract.on_withdraw(0, None, U128(to_yoto(10))));
+        assert!(contract.get_airline_balances(0).is_empty());
+    }
+
+    #[test]
+    fn withdraw_commission_failed() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+        contract.set_commission(1000);
+        claim_with_near(&mut contract, 1, 0);
+
+        testing_env!(context_airline);
+        contract.withdraw_commission(None, None);
+        assert!(contract.get_commissions().is_empty());
+
+        set_promise_result(PromiseResult::Failed);
+        assert!(!contract.on_withdraw_commission(None, U128(to_yoto(1))));
+        assert_eq!(
+            contract.get_commissions(),
+            vec![(None, U128(to_yoto(1)))]
+        );
+    }
+
+    #[test]
+    fn refund_failed_is_owed() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+        contract.grant_role("phuc16102001.testnet".to_string(), Role::Arbiter, None);
+        let flight_id = 1;
+        pay_with_token(&mut contract, flight_id, 10 * 10u128.pow(6));
+
+        testing_env!(context_airline);
+        contract.refund("thanhhoang4869.testnet".to_string(), flight_id);
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[1]["actions"][0]["FunctionCall"]["method_name"],
+            "on_refund"
+        );
+
+        set_promise_result(PromiseResult::Failed);
+        assert!(!contract.on_refund(
+            "thanhhoang4869.testnet".to_string(),
+            Some("usdc.testnet".to_string()),
+            U128(10 * 10u128.pow(6))
+        ));
+        assert_eq!(
+            contract.get_owed("thanhhoang4869.testnet".to_string()),
+            vec![(Some("usdc.testnet".to_string()), U128(10 * 10u128.pow(6)))]
+        );
+
+        testing_env!(get_context_customer(0));
+        contract.withdraw_owed(Some("usdc.testnet".to_string()));
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[0]["actions"][0]["FunctionCall"]["args"],
+            r#"{"receiver_id":"thanhhoang4869.testnet","amount":"10000000","memo":null}"#
+        );
+        assert!(contract.get_owed("thanhhoang4869.testnet".to_string()).is_empty());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Nothing is owed to you"#
+    )]
+    fn withdraw_owed_nothing() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.withdraw_owed(None);
+    }
+
+    #[test]
+    fn flight_allowance_overrides_class() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        assert_eq!(
+            contract.get_allowance(1, FlightClass::Economy),
+            Allowance::default()
+        );
+
+        let class_allowance = Allowance {
+            max_baggages: 2,
+            max_baggage_weight: Some(50.0),
+            max_total_weight: Some(80.0)
+        };
+        contract.set_class_allowance(FlightClass::Economy, Some(class_allowance.clone()));
+        assert_eq!(
+            contract.get_allowance(1, FlightClass::Economy),
+            class_allowance
+        );
+
+        let flight_allowance = Allowance {
+            max_baggages: 1,
+            max_baggage_weight: None,
+            max_total_weight: None
+        };
+        contract.set_flight_allowance(1, FlightClass::Economy, Some(flight_allowance.clone()));
+        assert_eq!(
+            contract.get_allowance(1, FlightClass::Economy),
+            flight_allowance
+        );
+        assert_eq!(
+            contract.get_allowance(1, FlightClass::Business),
+            Allowance::default()
+        );
+
+        contract.set_flight_allowance(1, FlightClass::Economy, None);
+        assert_eq!(
+            contract.get_allowance(1, FlightClass::Economy),
+            class_allowance
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"A baggage cannot weigh more than 50 lbs"#
+    )]
+    fn limit_baggage_weight() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.set_class_allowance(FlightClass::Economy, Some(Allowance {
+            max_baggages: 3,
+            max_baggage_weight: Some(50.0),
+            max_total_weight: None
+        }));
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Economy, 10.0);
+        contract.add_baggage(1, 50.0, None, None, None);
+        contract.add_baggage(1, 50.5, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Your baggages cannot weigh more than 80 lbs in total"#
+    )]
+    fn limit_total_weight() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.set_flight_allowance(1, FlightClass::Economy, Some(Allowance {
+            max_baggages: 3,
+            max_baggage_weight: None,
+            max_total_weight: Some(80.0)
+        }));
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Economy, 10.0);
+        contract.add_baggage(1, 40.0, None, None, None);
+        contract.add_baggage(1, 40.0, None, None, None);
+        contract.add_baggage(1, 1.0, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the airline admins can set the flight allowance"#
+    )]
+    fn set_flight_allowance_not_airline_admin() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.set_flight_allowance(1, FlightClass::Economy, None);
+    }
+
+    fn get_surcharge_policy() -> SurchargePolicy {
+        SurchargePolicy {
+            max_linear_dimension: 62.0,
+            oversize_surcharge: U128(100),
+            max_weight: 50.0,
+            overweight_surcharge: U128(75)
+        }
+    }
+
+    /// 22 + 14 + 9 inches
+    fn get_cabin_dimensions() -> Dimensions {
+        Dimensions {
+            length: 22.0,
+            width: 14.0,
+            height: 9.0
+        }
+    }
+
+    #[test]
+    fn fee_with_surcharges() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.set_surcharge_policy(FlightClass::Business, Some(get_surcharge_policy()));
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, Some(Dimensions {
+            length: 30.0,
+            width: 20.0,
+            height: 15.0
+        }), None, None);
+        contract.add_baggage(flight_id, 60.0, Some(get_cabin_dimensions()), None, None);
+
+        // 2*(5+60) + 100 (oversize) + 75 (overweight)
+        assert_eq!(contract.check_fee(flight_id), 305);
+        assert_eq!(
+            contract.check_surcharges(flight_id),
+            vec![
+                BaggageSurcharge {
+                    baggage_id: 0,
+                    linear_dimension: Some(65.0),
+                    oversize: true,
+                    overweight: false,
+                    surcharge: U128(100)
+                },
+                BaggageSurcharge {
+                    baggage_id: 1,
+                    linear_dimension: Some(45.0),
+                    oversize: false,
+                    overweight: true,
+                    surcharge: U128(75)
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn fee_without_surcharge_policy() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.set_surcharge_policy(FlightClass::First, Some(get_surcharge_policy()));
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 60.0, None, None, None);
+        assert_eq!(contract.check_fee(flight_id), 120);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The baggage dimensions are required in Business class"#
+    )]
+    fn add_baggage_without_dimensions() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.set_surcharge_policy(FlightClass::Business, Some(get_surcharge_policy()));
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The baggage dimensions must be positive"#
+    )]
+    fn add_baggage_invalid_dimensions() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, Some(Dimensions {
+            length: 30.0,
+            width: 0.0,
+            height: 15.0
+        }), None, None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the contract owner can set the surcharge policy"#
+    )]
+    fn set_surcharge_policy_not_owner() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.set_surcharge_policy(FlightClass::Business, None);
+    }
+
+    #[test]
+    fn fee_by_kind() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.set_surcharge_policy(FlightClass::Business, Some(get_surcharge_policy()));
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 10.0, Some(get_cabin_dimensions()), None, None);
+        let wheelchair = contract.add_baggage(flight_id, 60.0, None, Some(BaggageKind::MobilityAid), None);
+        contract.add_baggage(flight_id, 8.0, Some(get_cabin_dimensions()), Some(BaggageKind::Pet), None);
+
+        // 2*10 + 50 (pet), the wheelchair is free even when overweight
+        assert_eq!(contract.check_fee(flight_id), 70);
+        assert_eq!(
+            *contract.check_baggage(flight_id, wheelchair).get_kind(),
+            BaggageKind::MobilityAid
+        );
+        assert_eq!(
+            contract.check_surcharges(flight_id)[1].surcharge,
+            U128(0)
+        );
+    }
+
+    #[test]
+    fn set_kind_rule() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        let rule = KindRule {
+            pricing: KindPricing::Flat(U128(30)),
+            max_baggages: Some(1),
+            max_weight: Some(50.0)
+        };
+        contract.set_kind_rule(BaggageKind::SportsEquipment, Some(rule.clone()));
+        assert_eq!(
+            contract.get_kind_rule(BaggageKind::SportsEquipment),
+            rule
+        );
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Economy, 10.0);
+        contract.add_baggage(1, 20.0, None, Some(BaggageKind::SportsEquipment), None);
+        assert_eq!(contract.check_fee(1), 2 * 10 + 30);
+
+        testing_env!(get_context_airlines());
+        contract.set_kind_rule(BaggageKind::SportsEquipment, None);
+        assert_eq!(
+            contract.get_kind_rule(BaggageKind::SportsEquipment).pricing,
+            KindPricing::Weight
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"You cannot add more than 1 baggages of kind Pet"#
+    )]
+    fn limit_pets() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 8.0, None, Some(BaggageKind::Pet), None);
+        contract.add_baggage(1, 6.0, None, Some(BaggageKind::Pet), None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"A baggage of kind MusicalInstrument cannot weigh more than 30 lbs"#
+    )]
+    fn limit_kind_weight() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.set_kind_rule(BaggageKind::MusicalInstrument, Some(KindRule {
+            pricing: KindPricing::Weight,
+            max_baggages: None,
+            max_weight: Some(30.0)
+        }));
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 31.0, None, Some(BaggageKind::MusicalInstrument), None);
+    }
+
+    fn declare(item: DangerousItem, quantity: u32) -> Option<Vec<DeclaredItem>> {
+        Some(vec![DeclaredItem {
+            item,
+            quantity
+        }])
+    }
+
+    #[test]
+    fn clear_flagged_baggage() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline.clone());
+        let mut contract = setup_contract();
+        contract.grant_role("phuc16102001.testnet".to_string(), Role::CheckInAgent, Some(0));
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        let toiletries = contract.add_baggage(flight_id, 2.0, None, None, declare(DangerousItem::Aerosols, 3));
+        let laptop = contract.add_baggage(flight_id, 3.0, None, None, declare(DangerousItem::LithiumBatteries, 1));
+        assert_eq!(
+            *contract.check_baggage(flight_id, toiletries).get_inspection(),
+            InspectionState::NotRequired
+        );
+        assert_eq!(
+            *contract.check_baggage(flight_id, laptop).get_inspection(),
+            InspectionState::Pending
+        );
+
+        testing_env!(context_airline);
+        contract.clear_baggage("thanhhoang4869.testnet".to_string(), flight_id, laptop);
+
+        testing_env!(get_context_customer(0));
+        assert_eq!(
+            *contract.check_baggage(flight_id, laptop).get_inspection(),
+            InspectionState::Cleared
+        );
+        assert!(accept_with_price(&mut contract, flight_id, to_yoto(10), ONE_USD_PER_NEAR));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Baggage 0 must be inspected by the airline first"#
+    )]
+    fn accept_flagged_baggage() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, declare(DangerousItem::DryIce, 2));
+
+        testing_env!(get_context_customer(to_yoto(10)));
+        contract.accept(1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"SpareLithiumBatteries cannot be carried in a checked baggage"#
+    )]
+    fn add_forbidden_item() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, declare(DangerousItem::SpareLithiumBatteries, 2));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the check-in agents of the airline can clear a baggage"#
+    )]
+    fn clear_baggage_not_agent() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        let baggage_id = contract.add_baggage(1, 5.0, None, None, declare(DangerousItem::Aerosols, 6));
+        contract.clear_baggage("thanhhoang4869.testnet".to_string(), 1, baggage_id);
+    }
+
+    #[test]
+    fn aerosols_declared_twice_flagged() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        let aerosols = DeclaredItem {
+            item: DangerousItem::Aerosols,
+            quantity: 4
+        };
+        let baggage_id = contract.add_baggage(1, 5.0, None, None, Some(vec![aerosols.clone(), aerosols]));
+        assert_eq!(
+            *contract.check_baggage(1, baggage_id).get_inspection(),
+            InspectionState::Pending
+        );
+    }
+
+    fn record_weight(contract: &mut Contract, flight_id: FlightId, measured_weight: Weight) {
+        testing_env!(get_context_airlines());
+        contract.grant_role("phuc16102001.testnet".to_string(), Role::CheckInAgent, Some(0));
+        contract.record_weight(
+            "thanhhoang4869.testnet".to_string(),
+            flight_id,
+            0,
+            measured_weight
+        );
+    }
+
+    #[test]
+    fn record_weight_within_tolerance() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        contract.set_weight_tolerance(0, 1.0);
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+
+        record_weight(&mut contract, flight_id, 5.5);
+        testing_env!(get_context_customer(0));
+        assert_eq!(contract.check_fee(flight_id), 10);
+
+        record_weight(&mut contract, flight_id, 7.0);
+        testing_env!(get_context_customer(0));
+        assert_eq!(contract.check_fee(flight_id), 14);
+        assert_eq!(
+            contract.check_baggage(flight_id, 0).get_measured_weight(),
+            Some(7.0)
+        );
+    }
+
+    #[test]
+    fn record_weight_without_spare_storage() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+        testing_env!(get_context_customer(1));
+        contract.storage_withdraw(None);
+
+        record_weight(&mut contract, flight_id, 7.0);
+        testing_env!(get_context_customer(0));
+        assert_eq!(
+            contract.check_baggage(flight_id, 0).get_measured_weight(),
+            Some(7.0)
+        );
+    }
+
+    #[test]
+    fn record_weight_then_top_up() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+        assert!(accept_with_price(&mut contract, flight_id, to_yoto(10), ONE_USD_PER_NEAR));
+
+        record_weight(&mut contract, flight_id, 7.0);
+        testing_env!(get_context_customer(0));
+        assert_eq!(
+            contract.check_state(flight_id),
+            "AwaitingTopUp"
+        );
+
+        testing_env!(get_context_customer(to_yoto(5)));
+        contract.top_up(flight_id);
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[0]["actions"][0]["Transfer"]["deposit"].as_f64().unwrap(),
+            to_yoto(1) as f64
+        );
+        assert_eq!(
+            contract.check_state(flight_id),
+            "Checked"
+        );
+        let escrow = contract.check_escrow(flight_id).unwrap();
+        assert_eq!(escrow.get_amount(), to_yoto(14));
+        assert_eq!(escrow.get_fee(), 14);
+    }
+
+    #[test]
+    fn record_weight_then_refund() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        let flight_id = 1;
+        pay_with_token(&mut contract, flight_id, 10 * 10u128.pow(6));
+
+        record_weight(&mut contract, flight_id, 4.0);
+        let receipts = get_receipts();
+        assert_eq!(
+            receipts[0]["actions"][0]["FunctionCall"]["args"],
+            r#"{"receiver_id":"thanhhoang4869.testnet","amount":"2000000","memo":null}"#
+        );
+
+        testing_env!(get_context_customer(0));
+        assert_eq!(
+            contract.check_state(flight_id),
+            "Checked"
+        );
+        assert_eq!(
+            contract.check_escrow(flight_id).unwrap().get_amount(),
+            8 * 10u128.pow(6)
+        );
+    }
+
+    #[test]
+    fn top_up_with_token() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        let flight_id = 1;
+        pay_with_token(&mut contract, flight_id, 10 * 10u128.pow(6));
+        record_weight(&mut contract, flight_id, 6.0);
+
+        testing_env!(get_context_token());
+        contract.ft_on_transfer(
+            "thanhhoang4869.testnet".to_string(),
+            U128(2 * 10u128.pow(6)),
+            format!("{{\"flight_id\":{}}}", flight_id)
+        );
+
+        testing_env!(get_context_customer(0));
+        assert_eq!(
+            contract.check_state(flight_id),
+            "Checked"
+        );
+        assert_eq!(
+            contract.check_escrow(flight_id).unwrap().get_amount(),
+            12 * 10u128.pow(6)
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"You can only do this in Checked state, not AwaitingTopUp"#
+    )]
+    fn deliver_awaiting_top_up() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+        let flight_id = 1;
+        pay_with_token(&mut contract, flight_id, 10 * 10u128.pow(6));
+        record_weight(&mut contract, flight_id, 6.0);
+
+        testing_env!(get_context_airlines());
+        contract.deliver_baggage("thanhhoang4869.testnet".to_string(), flight_id, get_claim_code_hash());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the check-in agents of the airline can record a weight"#
+    )]
+    fn record_weight_not_agent() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+        contract.record_weight("thanhhoang4869.testnet".to_string(), 1, 0, 4.0);
+    }
+
+    #[test]
+    fn add_extra_baggage_then_top_up() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+        assert!(accept_with_price(&mut contract, flight_id, to_yoto(10), ONE_USD_PER_NEAR));
+
+        testing_env!(get_context_customer(0));
+        contract.add_extra_baggage(flight_id, 3.0, None, None, None);
+        assert_eq!(
+            contract.check_state(flight_id),
+            "AwaitingTopUp"
+        );
+        assert_eq!(contract.check_fee(flight_id), 16);
+
+        testing_env!(get_context_customer(to_yoto(6)));
+        contract.top_up(flight_id);
+        assert_eq!(
+            contract.check_state(flight_id),
+            "Checked"
+        );
+        assert_eq!(
+            contract.check_escrow(flight_id).unwrap().get_amount(),
+            to_yoto(16)
+        );
+    }
+
+    #[test]
+    fn add_free_extra_baggage() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+        assert!(accept_with_price(&mut contract, flight_id, to_yoto(10), ONE_USD_PER_NEAR));
+
+        testing_env!(get_context_customer(0));
+        contract.add_extra_baggage(flight_id, 30.0, None, Some(BaggageKind::MobilityAid), None);
+        assert_eq!(
+            contract.check_state(flight_id),
+            "Checked"
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Baggage 1 must be inspected by the airline first"#
+    )]
+    fn deliver_extra_baggage_not_inspected() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        let flight_id = 1;
+        testing_env!(get_context_customer(0));
+        contract.registry(flight_id, FlightClass::Business, 10.0);
+        contract.add_baggage(flight_id, 5.0, None, None, None);
+        assert!(accept_with_price(&mut contract, flight_id, to_yoto(10), ONE_USD_PER_NEAR));
+
+        testing_env!(get_context_customer(0));
+        contract.add_extra_baggage(
+            flight_id,
+            30.0,
+            None,
+            Some(BaggageKind::MobilityAid),
+            declare(DangerousItem::LithiumBatteries, 1)
+        );
+
+        testing_env!(get_context_airlines());
+        contract.deliver_baggage("thanhhoang4869.testnet".to_string(), flight_id, get_claim_code_hash());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"You can only add an extra baggage in Checked or AwaitingTopUp state, not Idle"#
+    )]
+    fn add_extra_baggage_before_payment() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_extra_baggage(1, 3.0, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"You cannot add more than 3 baggages"#
+    )]
+    fn add_extra_baggage_over_allowance() {
+        let context_airline = get_context_airlines();
+
+        testing_env!(context_airline);
+        let mut contract = setup_contract();
+
+        let flight_id = 1;
+        pay_with_token(&mut contract, flight_id, 10 * 10u128.pow(6));
+
+        testing_env!(get_context_customer(0));
+        contract.add_extra_baggage(flight_id, 1.0, None, None, None);
+        contract.add_extra_baggage(flight_id, 1.0, None, None, None);
+        contract.add_extra_baggage(flight_id, 1.0, None, None, None);
+    }
+
+    /// An itinerary from flight 1 of VN to flight 2 of QH, paid 10 NEAR
+    fn setup_itinerary() -> Contract {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        let airline_id = contract.add_airline(
+            "QH".to_string(),
+            "airline.testnet".to_string()
+        );
+        contract.grant_role(
+            "airline.testnet".to_string(),
+            Role::AirlineAdmin,
+            Some(airline_id)
+        );
+        contract.grant_role(
+            "airline.testnet".to_string(),
+            Role::GroundHandler,
+            Some(airline_id)
+        );
+
+        // 3 and 1 degrees along the equator
+        for (code, longitude) in [("AAA", 0), ("BBB", 3_000_000), ("CCC", 4_000_000)] {
+            contract.set_airport(code.to_string(), Some(Airport { latitude: 0, longitude }));
+        }
+        contract.set_flight_route(1, "AAA".to_string(), "BBB".to_string());
+
+        testing_env!(get_context(vec![], false, "airline.testnet".to_string(), 0));
+        contract.add_flight(airline_id, 2);
+        contract.set_flight_route(2, "BBB".to_string(), "CCC".to_string());
+
+        testing_env!(get_context_customer(0));
+        contract.registry_itinerary(
+            FlightClass::Business,
+            vec![Segment::new(1, 0.0), Segment::new(2, 0.0)]
+        );
+        contract.add_baggage(1, 5.0, None, None, None);
+        assert!(accept_with_price(&mut contract, 1, to_yoto(10), ONE_USD_PER_NEAR));
+        contract
+    }
+
+    #[test]
+    fn itinerary_split_between_airlines() {
+        let mut contract = setup_itinerary();
+
+        testing_env!(get_context_airlines());
+        contract.transfer_baggage("thanhhoang4869.testnet".to_string(), 1);
+        assert_eq!(
+            get_logs(),
+            vec!["Baggages transferred from flight 1 to flight 2"]
+        );
+
+        testing_env!(get_context(vec![], false, "airline.testnet".to_string(), 0));
+        contract.deliver_baggage("thanhhoang4869.testnet".to_string(), 1, get_claim_code_hash());
+
+        testing_env!(get_context_customer(0));
+        let segments = contract.check_itinerary(1);
+        assert!(segments[0].transferred);
+        assert!(!segments[1].transferred);
+        contract.claim_baggages(1, CLAIM_CODE.to_string());
+        assert_eq!(
+            contract.get_airline_balances(0),
+            vec![(None, U128(to_yoto(75) / 10))]
+        );
+        assert_eq!(
+            contract.get_airline_balances(1),
+            vec![(None, U128(to_yoto(25) / 10))]
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"You cannot add an extra baggage after the first flight departed"#
+    )]
+    fn itinerary_extra_baggage_after_transfer() {
+        let mut contract = setup_itinerary();
+
+        testing_env!(get_context_airlines());
+        contract.transfer_baggage("thanhhoang4869.testnet".to_string(), 1);
+
+        testing_env!(get_context_customer(0));
+        contract.add_extra_baggage(1, 3.0, None, None, None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"You cannot record a weight after the first flight departed"#
+    )]
+    fn itinerary_record_weight_after_transfer() {
+        let mut contract = setup_itinerary();
+
+        testing_env!(get_context_airlines());
+        contract.transfer_baggage("thanhhoang4869.testnet".to_string(), 1);
+        record_weight(&mut contract, 1, 7.0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The baggages must be transferred from flight 1 first"#
+    )]
+    fn itinerary_deliver_before_transfer() {
+        let mut contract = setup_itinerary();
+
+        testing_env!(get_context_airlines());
+        contract.deliver_baggage("thanhhoang4869.testnet".to_string(), 1, get_claim_code_hash());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the ground handlers of the airline can transfer the baggages"#
+    )]
+    fn itinerary_transfer_other_airline() {
+        let mut contract = setup_itinerary();
+
+        testing_env!(get_context(vec![], false, "airline.testnet".to_string(), 0));
+        contract.transfer_baggage("thanhhoang4869.testnet".to_string(), 1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The flight 2 has no route, it is needed to split the payout between airlines"#
+    )]
+    fn itinerary_interline_without_route() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        let airline_id = contract.add_airline(
+            "QH".to_string(),
+            "airline.testnet".to_string()
+        );
+        contract.grant_role(
+            "airline.testnet".to_string(),
+            Role::AirlineAdmin,
+            Some(airline_id)
+        );
+        set_airports(&mut contract);
+        contract.set_flight_route(1, "HAN".to_string(), "SGN".to_string());
+
+        testing_env!(get_context(vec![], false, "airline.testnet".to_string(), 0));
+        contract.add_flight(airline_id, 2);
+
+        testing_env!(get_context_customer(0));
+        contract.registry_itinerary(
+            FlightClass::Business,
+            vec![Segment::new(1, 0.0), Segment::new(2, 100.0)]
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The flight 1 is twice in the itinerary"#
+    )]
+    fn itinerary_same_flight_twice() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.registry_itinerary(
+            FlightClass::Business,
+            vec![Segment::new(1, 30.0), Segment::new(1, 10.0)]
+        );
+    }
+
+    fn set_airports(contract: &mut Contract) {
+        contract.set_airport(
+            "HAN".to_string(),
+            Some(Airport { latitude: 21_221_192, longitude: 105_807_178 })
+        );
+        contract.set_airport(
+            "SGN".to_string(),
+            Some(Airport { latitude: 10_818_463, longitude: 106_658_833 })
+        );
+    }
+
+    #[test]
+    fn airport_distance() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        set_airports(&mut contract);
+        contract.set_airport(
+            "JFK".to_string(),
+            Some(Airport { latitude: 40_641_311, longitude: -73_778_139 })
+        );
+        contract.set_airport(
+            "LHR".to_string(),
+            Some(Airport { latitude: 51_470_020, longitude: -454_295 })
+        );
+
+        assert_eq!(contract.get_distance("HAN".to_string(), "SGN".to_string()), 721.0);
+        assert_eq!(contract.get_distance("SGN".to_string(), "HAN".to_string()), 721.0);
+        assert_eq!(contract.get_distance("JFK".to_string(), "LHR".to_string()), 3442.0);
+        assert_eq!(contract.get_distance("HAN".to_string(), "HAN".to_string()), 0.0);
+    }
+
+    #[test]
+    fn route_sets_economy_distance() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        set_airports(&mut contract);
+        contract.set_flight_route(1, "HAN".to_string(), "SGN".to_string());
+        assert_eq!(
+            get_logs(),
+            vec![
+                "Airport HAN updated",
+                "Airport SGN updated",
+                "Route of flight 1 updated, 721 miles"
+            ]
+        );
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Economy, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+        assert_eq!(contract.check_fee(1), 2 * (5 + 721));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the contract owner can set an airport"#
+    )]
+    fn set_airport_not_owner() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        set_airports(&mut contract);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The airport code must be 3 capital letters"#
+    )]
+    fn set_airport_invalid_code() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        contract.set_airport(
+            "han".to_string(),
+            Some(Airport { latitude: 21_221_192, longitude: 105_807_178 })
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Cannot find the airport DAD"#
+    )]
+    fn route_unknown_airport() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        set_airports(&mut contract);
+        contract.set_flight_route(1, "HAN".to_string(), "DAD".to_string());
+    }
+
+    fn get_context_friend(attached_deposit: Balance) -> VMContext {
+        get_context(
+            vec![],
+            false,
+            "friend.testnet".to_string(),
+            attached_deposit
+        )
+    }
+
+    /// A First class group led by the customer, with `friend.testnet`
+    fn setup_group() -> Contract {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        deposit_storage(&mut contract, "friend.testnet");
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::First, 10.0);
+        let group_id = contract.create_group(1);
+        contract.invite_to_group(group_id, "friend.testnet".to_string());
+
+        testing_env!(get_context_friend(0));
+        contract.registry(1, FlightClass::First, 10.0);
+        contract.join_group(group_id);
+        contract
+    }
+
+    #[test]
+    fn group_pools_allowance_and_fee() {
+        let mut contract = setup_group();
+        assert_eq!(
+            contract.get_group(0).unwrap().get_members(),
+            &vec!["thanhhoang4869.testnet".to_string(), "friend.testnet".to_string()]
+        );
+
+        // 4 baggages fit in the allowance of 2 customers
+        testing_env!(get_context_friend(0));
+        contract.add_baggage(1, 20.0, None, None, None);
+        contract.add_baggage(1, 5.0, None, None, None);
+        contract.add_baggage(1, 5.0, None, None, None);
+        contract.add_baggage(1, 5.0, None, None, None);
+        testing_env!(get_context_customer(0));
+        contract.add_baggage(1, 10.0, None, None, None);
+
+        // The 2 heaviest baggages are free
+        assert_eq!(contract.check_fee(1), 30);
+        testing_env!(get_context_friend(0));
+        assert_eq!(contract.check_fee(1), 30);
+
+        assert!(accept_with_price(&mut contract, 1, to_yoto(30), ONE_USD_PER_NEAR));
+        testing_env!(get_context_friend(0));
+        assert_eq!(contract.check_state(1), "Checked");
+
+        testing_env!(get_context_airlines());
+        contract.deliver_baggage("friend.testnet".to_string(), 1, get_claim_code_hash());
+
+        testing_env!(get_context_customer(0));
+        assert_eq!(contract.check_state(1), "Delivered");
+        contract.claim_baggages(1, CLAIM_CODE.to_string());
+        assert_eq!(
+            contract.get_airline_balances(0),
+            vec![(None, U128(to_yoto(30)))]
+        );
+        testing_env!(get_context_friend(0));
+        assert_eq!(contract.check_state(1), "Claimed");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The leader thanhhoang4869.testnet of the group pays for your baggages"#
+    )]
+    fn group_member_cannot_pay() {
+        let mut contract = setup_group();
+
+        testing_env!(get_context_friend(0));
+        contract.add_baggage(1, 5.0, None, None, None);
+        testing_env!(get_context_friend(to_yoto(10)));
+        contract.accept(1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The leader thanhhoang4869.testnet of the group claims the baggages of friend.testnet"#
+    )]
+    fn group_member_cannot_authorize_pickup() {
+        let mut contract = setup_group();
+
+        testing_env!(get_context_friend(0));
+        contract.authorize_pickup(1, "courier.testnet".to_string(), None, None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The leader thanhhoang4869.testnet of the group claims the baggages of friend.testnet"#
+    )]
+    fn group_member_delegate_cannot_claim() {
+        let mut contract = setup_group();
+
+        // Authorized before joining the group
+        testing_env!(get_context_friend(0));
+        contract.leave_group(1);
+        contract.authorize_pickup(1, "courier.testnet".to_string(), None, None);
+        testing_env!(get_context_customer(0));
+        contract.invite_to_group(0, "friend.testnet".to_string());
+        testing_env!(get_context_friend(0));
+        contract.join_group(0);
+        contract.add_baggage(1, 5.0, None, None, None);
+
+        assert!(accept_with_price(&mut contract, 1, to_yoto(10), ONE_USD_PER_NEAR));
+        testing_env!(get_context_airlines());
+        contract.deliver_baggage("thanhhoang4869.testnet".to_string(), 1, get_claim_code_hash());
+
+        testing_env!(get_context(vec![], false, "courier.testnet".to_string(), 0));
+        contract.claim_for("friend.testnet".to_string(), 1, CLAIM_CODE.to_string());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"You are not invited to this group"#
+    )]
+    fn group_join_not_invited() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        deposit_storage(&mut contract, "friend.testnet");
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::First, 10.0);
+        let group_id = contract.create_group(1);
+
+        testing_env!(get_context_friend(0));
+        contract.registry(1, FlightClass::First, 10.0);
+        contract.join_group(group_id);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The baggages cannot be more than 3"#
+    )]
+    fn group_leave_over_allowance() {
+        let mut contract = setup_group();
+
+        testing_env!(get_context_friend(0));
+        for _ in 0..4 {
+            contract.add_baggage(1, 5.0, None, None, None);
+        }
+        contract.leave_group(1);
+    }
+
+    #[test]
+    fn sponsor_pays_and_is_refunded() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        contract.grant_role("arbiter.testnet".to_string(), Role::Arbiter, None);
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+        contract.approve_sponsor(1, Some("friend.testnet".to_string()));
+
+        testing_env!(get_context_friend(to_yoto(12)));
+        contract.accept_for("thanhhoang4869.testnet".to_string(), 1);
+        testing_env!(
+            get_context(vec![], false, "dev&-account.testnet".to_string(), 0),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            vec![PromiseResult::Successful(get_price_data(ONE_USD_PER_NEAR, 0))]
+        );
+        assert!(contract.on_accept_price(
+            "thanhhoang4869.testnet".to_string(),
+            "friend.testnet".to_string(),
+            1,
+            U128(10),
+            U128(to_yoto(12))
+        ));
+        // The excess goes back to the sponsor
+        let receipts = get_receipts();
+        assert_eq!(receipts[0]["receiver_id"], "friend.testnet");
+
+        testing_env!(get_context_customer(0));
+        assert_eq!(
+            contract.check_escrow(1).unwrap().get_payer(),
+            "friend.testnet"
+        );
+
+        testing_env!(get_context(vec![], false, "arbiter.testnet".to_string(), 0));
+        contract.refund("thanhhoang4869.testnet".to_string(), 1);
+        let receipts = get_receipts();
+        assert_eq!(receipts[0]["receiver_id"], "friend.testnet");
+    }
+
+    fn sponsor_accept(contract: &mut Contract) {
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+        contract.approve_sponsor(1, Some("friend.testnet".to_string()));
+
+        testing_env!(get_context_friend(to_yoto(10)));
+        contract.accept_for("thanhhoang4869.testnet".to_string(), 1);
+        testing_env!(
+            get_context(vec![], false, "dev&-account.testnet".to_string(), 0),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            vec![PromiseResult::Successful(get_price_data(ONE_USD_PER_NEAR, 0))]
+        );
+        assert!(contract.on_accept_price(
+            "thanhhoang4869.testnet".to_string(),
+            "friend.testnet".to_string(),
+            1,
+            U128(10),
+            U128(to_yoto(10))
+        ));
+    }
+
+    #[test]
+    fn sponsor_tops_up() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        sponsor_accept(&mut contract);
+        record_weight(&mut contract, 1, 7.0);
+
+        testing_env!(get_context_friend(to_yoto(5)));
+        contract.top_up_for("thanhhoang4869.testnet".to_string(), 1);
+        // The excess goes back to the sponsor
+        let receipts = get_receipts();
+        assert_eq!(receipts[0]["receiver_id"], "friend.testnet");
+        assert_eq!(
+            receipts[0]["actions"][0]["Transfer"]["deposit"].as_f64().unwrap(),
+            to_yoto(1) as f64
+        );
+
+        testing_env!(get_context_customer(0));
+        assert_eq!(contract.check_state(1), "Checked");
+        assert_eq!(contract.check_escrow(1).unwrap().get_amount(), to_yoto(14));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only friend.testnet who paid the deposit can top it up"#
+    )]
+    fn customer_tops_up_sponsored() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        sponsor_accept(&mut contract);
+        record_weight(&mut contract, 1, 7.0);
+
+        testing_env!(get_context_customer(to_yoto(4)));
+        contract.top_up(1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only friend.testnet who paid the deposit can top it up"#
+    )]
+    fn customer_tops_up_sponsored_with_token() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        contract.add_token("usdc.testnet".to_string(), 6);
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+        contract.approve_sponsor(1, Some("friend.testnet".to_string()));
+
+        testing_env!(get_context_token());
+        contract.ft_on_transfer(
+            "friend.testnet".to_string(),
+            U128(10 * 10u128.pow(6)),
+            r#"{"flight_id":1,"customer_id":"thanhhoang4869.testnet"}"#.to_string()
+        );
+        record_weight(&mut contract, 1, 7.0);
+
+        testing_env!(get_context_token());
+        contract.ft_on_transfer(
+            "thanhhoang4869.testnet".to_string(),
+            U128(4 * 10u128.pow(6)),
+            r#"{"flight_id":1}"#.to_string()
+        );
+    }
+
+    #[test]
+    fn sponsor_pays_with_token() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        contract.add_token("usdc.testnet".to_string(), 6);
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+        contract.approve_sponsor(1, Some("friend.testnet".to_string()));
+
+        testing_env!(get_context_token());
+        contract.ft_on_transfer(
+            "friend.testnet".to_string(),
+            U128(10 * 10u128.pow(6)),
+            r#"{"flight_id":1,"customer_id":"thanhhoang4869.testnet"}"#.to_string()
+        );
+
+        testing_env!(get_context_customer(0));
+        assert_eq!(contract.check_state(1), "Checked");
+        assert_eq!(
+            contract.check_escrow(1).unwrap().get_payer(),
+            "friend.testnet"
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Cannot find the flight of thanhhoang4869.testnet"#
+    )]
+    fn sponsor_unknown_flight() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_friend(to_yoto(10)));
+        contract.accept_for("thanhhoang4869.testnet".to_string(), 1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"friend.testnet is not approved to pay for thanhhoang4869.testnet"#
+    )]
+    fn sponsor_not_approved() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+
+        testing_env!(get_context_friend(to_yoto(10)));
+        contract.accept_for("thanhhoang4869.testnet".to_string(), 1);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"friend.testnet is not approved to pay for thanhhoang4869.testnet"#
+    )]
+    fn sponsor_approval_withdrawn_with_token() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        contract.add_token("usdc.testnet".to_string(), 6);
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+        contract.approve_sponsor(1, Some("friend.testnet".to_string()));
+        contract.approve_sponsor(1, None);
+
+        testing_env!(get_context_token());
+        contract.ft_on_transfer(
+            "friend.testnet".to_string(),
+            U128(10 * 10u128.pow(6)),
+            r#"{"flight_id":1,"customer_id":"thanhhoang4869.testnet"}"#.to_string()
+        );
+    }
+
+    /// Two delivered baggages of 5 lbs, paid 20 NEAR
+    fn setup_pickup(baggage_ids: Option<Vec<BaggageId>>, expires_at: Option<U64>) -> Contract {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+        contract.add_baggage(1, 5.0, None, None, None);
+        assert!(accept_with_price(&mut contract, 1, to_yoto(20), ONE_USD_PER_NEAR));
+
+        testing_env!(get_context_customer(0));
+        contract.authorize_pickup(1, "friend.testnet".to_string(), baggage_ids, expires_at);
+
+        testing_env!(get_context_airlines());
+        contract.deliver_baggage("thanhhoang4869.testnet".to_string(), 1, get_claim_code_hash());
+        contract
+    }
+
+    #[test]
+    fn delegate_claims_some_baggages() {
+        let mut contract = setup_pickup(Some(vec![0]), Some(U64(1000)));
+        assert_eq!(
+            contract.get_pickups("thanhhoang4869.testnet".to_string(), 1),
+            vec![Delegation {
+                delegate: "friend.testnet".to_string(),
+                baggage_ids: Some(vec![0]),
+                expires_at: Some(U64(1000))
+            }]
+        );
+
+        testing_env!(get_context_friend(0));
+        contract.claim_for("thanhhoang4869.testnet".to_string(), 1, CLAIM_CODE.to_string());
+        assert_eq!(
+            get_logs(),
+            vec!["Baggages [0] of thanhhoang4869.testnet claimed by friend.testnet"]
+        );
+        assert!(contract.get_airline_balances(0).is_empty());
+
+        testing_env!(get_context_customer(0));
+        assert_eq!(contract.check_state(1), "Delivered");
+        assert!(contract.check_baggage(1, 0).is_claimed());
+        contract.claim_baggages(1, CLAIM_CODE.to_string());
+        assert_eq!(contract.check_state(1), "Claimed");
+        assert_eq!(
+            contract.get_airline_balances(0),
+            vec![(None, U128(to_yoto(20)))]
+        );
+    }
+
+    #[test]
+    fn delegate_claims_all_baggages() {
+        let mut contract = setup_pickup(None, None);
+
+        testing_env!(get_context_friend(0));
+        contract.claim_for("thanhhoang4869.testnet".to_string(), 1, CLAIM_CODE.to_string());
+
+        testing_env!(get_context_customer(0));
+        assert_eq!(contract.check_state(1), "Claimed");
+        assert_eq!(
+            contract.get_airline_balances(0),
+            vec![(None, U128(to_yoto(20)))]
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Your pickup of the baggages of thanhhoang4869.testnet expired"#
+    )]
+    fn delegate_pickup_expired() {
+        let mut contract = setup_pickup(None, Some(U64(1000)));
+
+        let mut context = get_context_friend(0);
+        context.block_timestamp = 1000;
+        testing_env!(context);
+        contract.claim_for("thanhhoang4869.testnet".to_string(), 1, CLAIM_CODE.to_string());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"You cannot claim the baggages of thanhhoang4869.testnet"#
+    )]
+    fn delegate_pickup_revoked() {
+        let mut contract = setup_pickup(None, None);
+
+        testing_env!(get_context_customer(0));
+        contract.revoke_pickup(1, "friend.testnet".to_string());
+        assert!(contract.get_pickups("thanhhoang4869.testnet".to_string(), 1).is_empty());
+
+        testing_env!(get_context_friend(0));
+        contract.claim_for("thanhhoang4869.testnet".to_string(), 1, CLAIM_CODE.to_string());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The claim code is wrong"#
+    )]
+    fn claim_wrong_code() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+        assert!(accept_with_price(&mut contract, 1, to_yoto(10), ONE_USD_PER_NEAR));
+
+        testing_env!(get_context_airlines());
+        contract.deliver_baggage("thanhhoang4869.testnet".to_string(), 1, get_claim_code_hash());
+
+        testing_env!(get_context_customer(0));
+        contract.claim_baggages(1, "0000-0000-0000".to_string());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The claim code is wrong"#
+    )]
+    fn delegate_wrong_code() {
+        let mut contract = setup_pickup(None, None);
+
+        testing_env!(get_context_friend(0));
+        contract.claim_for("thanhhoang4869.testnet".to_string(), 1, "0000-0000-0000".to_string());
+    }
+
+    fn setup_loyalty(tier: LoyaltyTier) -> Contract {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        let airline_id = *contract.get_flight(1).get_airline_id();
+        contract.set_loyalty_tier(airline_id, "thanhhoang4869.testnet".to_string(), Some(tier));
+        assert_eq!(
+            contract.get_loyalty_tier(airline_id, "thanhhoang4869.testnet".to_string()),
+            Some(tier)
+        );
+
+        testing_env!(get_context_customer(0));
+        contract.registry(1, FlightClass::Business, 10.0);
+        contract.add_baggage(1, 5.0, None, None, None);
+        contract.add_baggage(1, 2.0, None, None, None);
+        contract.add_baggage(1, 3.0, None, None, None);
+        contract
+    }
+
+    #[test]
+    fn loyalty_free_baggage() {
+        let mut contract = setup_loyalty(LoyaltyTier::Gold);
+
+        // The heaviest baggage is free: 2*(2+3)
+        assert_eq!(contract.check_fee(1), 10);
+        assert_eq!(
+            contract.check_fee_quote("thanhhoang4869.testnet".to_string(), 1),
+            FeeQuote {
+                class_fee: U128(20),
+                tier: Some(LoyaltyTier::Gold),
+                free_baggages: vec![0],
+                free_baggages_discount: U128(10),
+                tier_discount: U128(0),
+                total: U128(10)
+            }
+        );
+    }
+
+    #[test]
+    fn loyalty_discount() {
+        let mut contract = setup_loyalty(LoyaltyTier::Silver);
+        assert_eq!(contract.check_fee(1), 18);
+
+        testing_env!(get_context_airlines());
+        let benefit = TierBenefit {
+            discount: 5_000,
+            free_baggages: 1
+        };
+        contract.set_tier_benefit(0, LoyaltyTier::Silver, Some(benefit.clone()));
+        assert_eq!(contract.get_tier_benefit(0, LoyaltyTier::Silver), benefit);
+
+        testing_env!(get_context_customer(0));
+        // 2*(2+3) without the heaviest baggage, then half of it
+        assert_eq!(contract.check_fee(1), 5);
+
+        testing_env!(get_context_airlines());
+        contract.set_loyalty_tier(0, "thanhhoang4869.testnet".to_string(), None);
+        let quote = contract.check_fee_quote("thanhhoang4869.testnet".to_string(), 1);
+        assert_eq!(quote.tier, None);
+        assert_eq!(quote.total, U128(20));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"Only the airline admins can set a loyalty tier"#
+    )]
+    fn set_loyalty_tier_not_airline_admin() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+
+        testing_env!(get_context_customer(0));
+        contract.set_loyalty_tier(0, "thanhhoang4869.testnet".to_string(), Some(LoyaltyTier::Platinum));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = r#"The discount cannot be more than 10000 basis points"#
+    )]
+    fn set_tier_benefit_too_high() {
+        testing_env!(get_context_airlines());
+        let mut contract = setup_contract();
+        contract.set_tier_benefit(
+            0,
+            LoyaltyTier::Gold,
+            Some(TierBenefit {
+                discount: 10_001,
+                free_baggages: 0
+            })
+        );
+    }
+}